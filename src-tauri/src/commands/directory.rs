@@ -0,0 +1,53 @@
+//! Tauri command handlers for directory ingestion.
+
+use std::path::Path;
+use tauri::State;
+use tracing::debug;
+use crate::commands::optimize_images;
+use crate::core::{AppState, DirectoryOptions, ImageSettings, ImageTask};
+use crate::utils::{OptimizerError, OptimizerResult, collect_images};
+
+/// Recursively optimizes every supported image under `path`.
+///
+/// The tree is scanned in Rust with the include/exclude globs, depth limit,
+/// symlink policy, and hidden-file rule from `options`. With an `outputRoot`
+/// the source tree is mirrored into it; otherwise each task's output follows
+/// the output template, which sees `path` as its `sourceRoot`. Processing is
+/// delegated to [`optimize_images`], so this returns the started job's id
+/// (`None` when no images were found) and progress arrives through the
+/// usual `image_optimization_progress` events.
+#[tauri::command]
+pub async fn optimize_directory(
+    state: State<'_, AppState>,
+    path: String,
+    settings: ImageSettings,
+    options: Option<DirectoryOptions>,
+) -> OptimizerResult<Option<String>> {
+    let options = options.unwrap_or_default();
+    debug!("Received optimize_directory command for {}", path);
+
+    let tasks = tokio::task::spawn_blocking(move || {
+        build_directory_tasks(Path::new(&path), &settings, &options)
+    })
+    .await
+    .map_err(|e| OptimizerError::processing(format!("Directory scan panicked: {e}")))??;
+
+    if tasks.is_empty() {
+        debug!("No supported images found");
+        return Ok(None);
+    }
+    optimize_images(state, tasks).await.map(Some)
+}
+
+/// Builds one task per image found under `root`.
+fn build_directory_tasks(
+    root: &Path,
+    settings: &ImageSettings,
+    options: &DirectoryOptions,
+) -> OptimizerResult<Vec<ImageTask>> {
+    let images = collect_images(root, options)?;
+    Ok(images
+        .iter()
+        .map(|image| ImageTask::from_directory_entry(root, image, settings, options))
+        .collect())
+}
@@ -1,77 +1,152 @@
 //! Tauri command handlers for image optimization.
 
-use std::time::Instant;
+use std::sync::Arc;
 use tauri::State;
-use tracing::debug;
-use crate::core::{AppState, ImageSettings, OptimizationResult};
+use tracing::{debug, warn};
+use crate::core::{AppState, ImageSettings, Job, OptimizationResult};
 use crate::core::ImageTask;
-use crate::utils::{OptimizerResult, validate_task};
+use crate::processing::progress::RunClock;
+use crate::processing::template::resolve_output_paths;
+use crate::utils::{OptimizerError, OptimizerResult, validate_batch};
 
 /// Optimizes a single image with the given settings.
 ///
-/// Convenience wrapper around [`optimize_images`] for single-image operations.
+/// Unlike [`optimize_images`] this waits for the image and returns its
+/// result; it still runs as a registered job, so progress events carry an id.
+/// When `output_path` is omitted it is rendered from the output template.
 #[tauri::command]
 pub async fn optimize_image(
     state: State<'_, AppState>,
     input_path: String,
-    output_path: String,
+    output_path: Option<String>,
     settings: ImageSettings,
 ) -> OptimizerResult<OptimizationResult> {
-    optimize_images(
-        state,
+    let job = prepare_job(
+        &state,
         vec![ImageTask {
             input_path,
-            output_path,
+            output_path: output_path.unwrap_or_default(),
             settings,
-        }]
+        }],
     )
-    .await
-    .and_then(|results| results.into_iter().next().ok_or_else(|| {
-        crate::utils::OptimizerError::processing("No result returned".to_string())
-    }))
+    .await?;
+
+    run_job(&state, &job)
+        .await
+        .and_then(|results| results.into_iter().next().ok_or_else(|| {
+            OptimizerError::processing("No result returned".to_string())
+        }))
 }
 
-/// Optimizes multiple images in batch with progress tracking.
+/// Starts a batch optimization job and returns its id immediately.
+///
+/// Tasks with an empty `output_path` get one rendered from their output
+/// template, then the whole batch is validated; both happen before this
+/// returns, so invalid batches are still rejected synchronously.
 ///
-/// Images are processed in chunks of 500 to keep memory bounded.
-/// Progress events use **overall** job counts (not per-chunk) so the
-/// frontend receives a simple monotonic stream from 1..N.
+/// The validated tasks are written to a durable job queue, so a crash or
+/// quit mid-batch can be resumed on the next launch (see
+/// [`resume_job`](super::resume_job)). The executor drains the queue one
+/// image at a time and holds back an image while the shared `maxMemory`
+/// budget cannot cover it. Progress events carry the job id
+/// and **overall** job counts (not per-chunk), so the frontend receives a
+/// simple monotonic stream from 1..N per job. Results are available through
+/// [`get_job_results`](super::get_job_results).
 ///
 /// # Arguments
 /// * `state` - Application state containing the executor
 /// * `tasks` - Vector of image tasks to process
 ///
 /// # Returns
-/// Vector of optimization results, one per input task.
+/// The id of the started job.
 #[tauri::command]
 pub async fn optimize_images(
     state: State<'_, AppState>,
     tasks: Vec<ImageTask>,
+) -> OptimizerResult<String> {
+    debug!("Received optimize_images command for {} images", tasks.len());
+    let job = prepare_job(&state, tasks).await?;
+    let id = job.id().to_string();
+    spawn_job(state.inner().clone(), job);
+    Ok(id)
+}
+
+/// Renders output paths, validates the batch, and registers it as a running job.
+pub(crate) async fn prepare_job(
+    state: &AppState,
+    tasks: Vec<ImageTask>,
+) -> OptimizerResult<Arc<Job>> {
+    // Templates may hash or probe source files, so render off the async runtime.
+    let tasks = tokio::task::spawn_blocking(move || {
+        let mut tasks = tasks;
+        resolve_output_paths(&mut tasks).map(|_| tasks)
+    })
+    .await
+    .map_err(|e| OptimizerError::processing(format!("Output path resolution panicked: {e}")))??;
+
+    validate_batch(&tasks).await?;
+
+    state.create_job(tasks)
+}
+
+/// Runs `job` in the background; the outcome is recorded on the job itself.
+pub(crate) fn spawn_job(state: AppState, job: Arc<Job>) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = run_job(&state, &job).await {
+            warn!("Job {} stopped: {e}", job.id());
+        }
+    });
+}
+
+/// Tasks processed between saves of the result cache.
+const CACHE_FLUSH_INTERVAL: usize = 500;
+
+/// Drains the job's queue and records how the run ended.
+///
+/// The result cache is saved every [`CACHE_FLUSH_INTERVAL`] tasks. Memory is
+/// bounded by the shared `maxMemory` budget, which every image reserves its
+/// decoded estimate from before it starts.
+///
+/// A completed queue is deleted; if the executor bails out early the queue
+/// is left on disk so the job can be resumed.
+pub(crate) async fn run_job(
+    state: &AppState,
+    job: &Job,
 ) -> OptimizerResult<Vec<OptimizationResult>> {
-    let job_total = tasks.len();
-    let job_start = Instant::now();
-    debug!("Received optimize_images command for {} images", job_total);
-    
-    for task in &tasks {
-        validate_task(task).await?;
-    }
+    let queue = job.queue();
+    let clock = RunClock::start(queue.finished());
+    let job_total = queue.total();
+    debug!(
+        "Processing job {} ({} images, {} already finished)",
+        job.id(),
+        job_total,
+        queue.finished()
+    );
 
-    const CHUNK_SIZE: usize = 500;
-    let chunks: Vec<_> = tasks.chunks(CHUNK_SIZE).collect();
-    debug!("Processing {} images in {} chunks of size {}", job_total, chunks.len(), CHUNK_SIZE);
-    
-    let mut all_results = Vec::with_capacity(job_total);
     let executor = state.create_executor();
-    let mut offset = 0;
-    
-    for (i, chunk) in chunks.iter().enumerate() {
-        debug!("Processing chunk {}/{} ({} images)", i + 1, chunks.len(), chunk.len());
-        let results = executor.execute_batch(chunk, offset, job_total, job_start).await?;
-        offset += chunk.len();
-        all_results.extend(results);
-        debug!("Completed chunk {}/{} ({}/{})", i + 1, chunks.len(), offset, job_total);
+    let plan = match executor.preflight(queue, job.pause_gate(), &clock).await {
+        Ok(plan) => plan,
+        Err(e) => {
+            job.finish(Some(e.to_string()));
+            return Err(e);
+        }
+    };
+    loop {
+        let pause = job.pause_gate();
+        match executor.execute_batch(queue, pause, &plan, CACHE_FLUSH_INTERVAL, &clock).await {
+            Ok(results) if results.is_empty() => break,
+            Ok(_) => debug!("Job {}: saved cache ({}/{})", job.id(), queue.finished(), job_total),
+            Err(e) => {
+                job.finish(Some(e.to_string()));
+                return Err(e);
+            }
+        }
     }
-    
-    debug!("All chunks processed, returning {} results", all_results.len());
+
+    queue.remove_files();
+    job.finish(None);
+    executor.emit_job_finished(queue, &clock);
+    let all_results = queue.results();
+    debug!("Job {} finished, returning {} results", job.id(), all_results.len());
     Ok(all_results)
 }
@@ -0,0 +1,120 @@
+//! Tauri command handlers for querying, pausing, resuming, and discarding jobs.
+
+use tauri::State;
+use tracing::debug;
+use crate::core::{AppState, JobStatus, OptimizationResult, Priority, QueueSummary};
+use crate::utils::OptimizerResult;
+use crate::utils::error::ValidationError;
+
+use super::image::spawn_job;
+
+/// Returns the progress and state of one job.
+#[tauri::command]
+pub async fn get_job_status(
+    state: State<'_, AppState>,
+    id: String,
+) -> OptimizerResult<JobStatus> {
+    state
+        .job(&id)
+        .map(|job| job.status())
+        .ok_or_else(|| unknown_job(&id).into())
+}
+
+/// Lists every job of this session, plus interrupted jobs from earlier sessions.
+#[tauri::command]
+pub async fn list_jobs(
+    state: State<'_, AppState>,
+) -> OptimizerResult<Vec<JobStatus>> {
+    Ok(state.list_jobs())
+}
+
+/// Returns the results of a job's finished tasks, in task order.
+///
+/// Can be called while the job runs to fetch partial results.
+#[tauri::command]
+pub async fn get_job_results(
+    state: State<'_, AppState>,
+    id: String,
+) -> OptimizerResult<Vec<OptimizationResult>> {
+    state
+        .job(&id)
+        .map(|job| job.results())
+        .ok_or_else(|| unknown_job(&id).into())
+}
+
+/// Lists jobs a previous session left unfinished, so the frontend can offer to resume them.
+#[tauri::command]
+pub async fn list_resumable_jobs(
+    state: State<'_, AppState>,
+) -> OptimizerResult<Vec<QueueSummary>> {
+    Ok(state.resumable_jobs())
+}
+
+/// Pauses a running job once its current image finishes.
+///
+/// Returns `false` when the job is not running or already paused.
+#[tauri::command]
+pub async fn pause_job(
+    state: State<'_, AppState>,
+    id: String,
+) -> OptimizerResult<bool> {
+    debug!("Received pause_job command for {}", id);
+    let job = state.job(&id).ok_or_else(|| unknown_job(&id))?;
+    Ok(job.pause())
+}
+
+/// Resumes a paused job, or restarts an interrupted or failed job in the
+/// background, processing only its pending tasks. Returns the job id, which
+/// progress events keep using.
+#[tauri::command]
+pub async fn resume_job(
+    state: State<'_, AppState>,
+    id: String,
+) -> OptimizerResult<String> {
+    debug!("Received resume_job command for {}", id);
+    let job = state.job(&id).ok_or_else(|| unknown_job(&id))?;
+    if job.unpause() {
+        return Ok(id);
+    }
+    if !job.try_restart() {
+        return Err(ValidationError::settings(format!("Job {id} is not resumable")).into());
+    }
+    spawn_job(state.inner().clone(), job);
+    Ok(id)
+}
+
+/// Discards an interrupted or failed job. Returns `false` when no such job exists.
+#[tauri::command]
+pub async fn discard_job(
+    state: State<'_, AppState>,
+    id: String,
+) -> OptimizerResult<bool> {
+    debug!("Received discard_job command for {}", id);
+    Ok(state.discard_job(&id))
+}
+
+/// Sets the processing priority for all jobs.
+///
+/// `background` limits libvips to a single worker thread so the machine stays
+/// usable; `normal` restores one thread per core.
+#[tauri::command]
+pub async fn set_priority(
+    state: State<'_, AppState>,
+    priority: Priority,
+) -> OptimizerResult<()> {
+    debug!("Received set_priority command: {:?}", priority);
+    state.set_priority(priority);
+    Ok(())
+}
+
+/// Returns the current processing priority.
+#[tauri::command]
+pub async fn get_priority(
+    state: State<'_, AppState>,
+) -> OptimizerResult<Priority> {
+    Ok(state.priority())
+}
+
+fn unknown_job(id: &str) -> ValidationError {
+    ValidationError::settings(format!("No job with id {id}"))
+}
@@ -0,0 +1,27 @@
+//! Tauri command handlers for per-image resource limits.
+
+use tauri::State;
+use tracing::debug;
+use crate::core::{AppState, ResourceLimits};
+use crate::utils::OptimizerResult;
+
+/// Replaces the resource limits for all jobs.
+///
+/// Images over a limit fail with a `too_large` error before they are
+/// decoded. The libvips cache caps apply immediately.
+#[tauri::command]
+pub async fn set_limits(
+    state: State<'_, AppState>,
+    limits: ResourceLimits,
+) -> OptimizerResult<()> {
+    debug!("Received set_limits command: {:?}", limits);
+    state.set_limits(limits)
+}
+
+/// Returns the current resource limits.
+#[tauri::command]
+pub async fn get_limits(
+    state: State<'_, AppState>,
+) -> OptimizerResult<ResourceLimits> {
+    Ok(state.limits())
+}
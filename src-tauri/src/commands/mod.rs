@@ -2,8 +2,29 @@
 //!
 //! This module exposes commands that can be invoked from the React frontend:
 //! - [`optimize_image`]: Optimize a single image
-//! - [`optimize_images`]: Batch optimize multiple images
+//! - [`optimize_images`]: Start a batch optimization job
+//! - [`optimize_directory`]: Recursively optimize a directory tree
+//! - [`get_job_status`] / [`list_jobs`] / [`get_job_results`]: Query jobs by id
+//! - [`pause_job`] / [`resume_job`]: Pause and resume a running job
+//! - [`list_resumable_jobs`] / [`discard_job`]: Resume or drop interrupted jobs
+//! - [`set_priority`] / [`get_priority`]: Normal or background processing priority
+//! - [`set_limits`] / [`get_limits`]: Per-image size limits and libvips cache caps
+//! - [`probe_images`]: Inspect image headers and flag unreadable files
+//! - [`export_report`]: Export a job summary as JSON, CSV, or Markdown
+//! - [`start_watch`] / [`stop_watch`] / [`list_watches`]: Watch-folder mode
 
+mod directory;
 mod image;
+mod jobs;
+mod limits;
+mod probe;
+mod report;
+mod watch;
 
+pub use directory::*;
 pub use image::*;
+pub use jobs::*;
+pub use limits::*;
+pub use probe::*;
+pub use report::*;
+pub use watch::*;
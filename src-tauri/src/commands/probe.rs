@@ -0,0 +1,27 @@
+//! Tauri command handler for inspecting images before optimizing them.
+
+use tauri::State;
+use tracing::debug;
+use crate::core::AppState;
+use crate::processing::libvips::{ImageProbe, probe_image};
+use crate::utils::{OptimizerError, OptimizerResult};
+
+/// Opens each file header-only and reports its dimensions, bands, format,
+/// page count, ICC profile, orientation, and estimated decoded size.
+///
+/// Truncated, corrupt, and too-large files are reported with a coded
+/// `error` rather than failing the command; the same checks run as the
+/// pre-flight stage of every job, against the current resource limits.
+#[tauri::command]
+pub async fn probe_images(
+    state: State<'_, AppState>,
+    paths: Vec<String>,
+) -> OptimizerResult<Vec<ImageProbe>> {
+    debug!("Received probe_images command for {} files", paths.len());
+    let limits = state.limits();
+    tokio::task::spawn_blocking(move || {
+        paths.iter().map(|path| probe_image(path, &limits)).collect()
+    })
+    .await
+    .map_err(|e| OptimizerError::processing(format!("Probe panicked: {e}")))
+}
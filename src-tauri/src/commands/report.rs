@@ -0,0 +1,35 @@
+//! Tauri command handlers for batch reports.
+
+use tauri::State;
+use tracing::debug;
+use crate::core::AppState;
+use crate::processing::report::{Report, ReportFormat};
+use crate::utils::{OptimizerError, OptimizerResult};
+use crate::utils::error::ValidationError;
+
+/// Writes a summary report for a job to `path`.
+///
+/// `format` defaults to the one implied by the file extension
+/// (`.json`, `.csv`, `.md`). Reports can be exported while a job is still
+/// running; they then cover the images finished so far.
+#[tauri::command]
+pub async fn export_report(
+    state: State<'_, AppState>,
+    job_id: String,
+    path: String,
+    format: Option<ReportFormat>,
+) -> OptimizerResult<()> {
+    debug!("Received export_report command for job {} to {}", job_id, path);
+    let format = match format {
+        Some(format) => format,
+        None => ReportFormat::from_path(&path)?,
+    };
+    let job = state
+        .job(&job_id)
+        .ok_or_else(|| ValidationError::settings(format!("No job with id {job_id}")))?;
+    let results = job.results();
+
+    tokio::task::spawn_blocking(move || Report::new(&results).write(&path, format))
+        .await
+        .map_err(|e| OptimizerError::processing(format!("Report export panicked: {e}")))?
+}
@@ -0,0 +1,40 @@
+//! Tauri command handlers for watch-folder mode.
+
+use tauri::State;
+use tracing::debug;
+use crate::core::{AppState, WatchConfig};
+use crate::utils::OptimizerResult;
+use crate::utils::validation::{validate_backup_settings, validate_settings};
+
+/// Starts watching a folder and optimizing images as they settle.
+///
+/// Settings are validated up front so a bad configuration fails here
+/// rather than once per file inside the watch loop.
+#[tauri::command]
+pub async fn start_watch(
+    state: State<'_, AppState>,
+    config: WatchConfig,
+) -> OptimizerResult<()> {
+    debug!("Received start_watch command for {}", config.path);
+    validate_settings(&config.settings)?;
+    validate_backup_settings(&config.settings)?;
+    state.start_watch(config)
+}
+
+/// Stops watching a folder. Returns `false` when it was not being watched.
+#[tauri::command]
+pub async fn stop_watch(
+    state: State<'_, AppState>,
+    path: String,
+) -> OptimizerResult<bool> {
+    debug!("Received stop_watch command for {}", path);
+    Ok(state.stop_watch(&path))
+}
+
+/// Lists the configurations of all active watches.
+#[tauri::command]
+pub async fn list_watches(
+    state: State<'_, AppState>,
+) -> OptimizerResult<Vec<WatchConfig>> {
+    Ok(state.watch_configs())
+}
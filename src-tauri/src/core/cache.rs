@@ -0,0 +1,335 @@
+//! Persistent cache of finished optimizations for incremental re-runs.
+//!
+//! Entries are keyed on the SHA-256 of the input content, the serialized
+//! [`ImageSettings`](crate::core::ImageSettings), the encoder version, and
+//! the resolved output path. Each entry records the output's path, size,
+//! modification time, and hash so a hit is only trusted when the output is
+//! still on disk unchanged; missing or corrupted outputs count as misses and
+//! are regenerated. An output whose size and modification time still match
+//! is trusted without re-hashing it. At most [`MAX_ENTRIES`] entries are
+//! kept; the least recently recorded are dropped first.
+//!
+//! The cache also remembers the hash of every output it has written together
+//! with a fingerprint of the settings that produced it, so a previous output
+//! fed back in as input (e.g. a dropped `optimized/` folder) is recognised
+//! and not recompressed with the same settings. Changed settings re-encode it.
+//! At most [`MAX_PRODUCED`] outputs are remembered; the oldest are forgotten.
+
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use crate::core::{
+    BackupSettings, ImageDetails, ImageTask, OptimizationResult, OptimizationStatus,
+    PhaseTimings,
+};
+use crate::processing::output::StagedOutput;
+use crate::utils::hash::to_hex;
+use crate::utils::{OptimizerError, OptimizerResult, extract_filename, file_sha256};
+
+/// File name of the cache inside the app data directory.
+pub const CACHE_FILE_NAME: &str = "optimization-cache.json";
+
+/// Most produced outputs remembered for prior-output detection.
+const MAX_PRODUCED: usize = 50_000;
+
+/// Most finished optimizations kept in the cache.
+const MAX_ENTRIES: usize = 50_000;
+
+/// One finished optimization.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    output_path: String,
+    output_hash: String,
+    output_size: u64,
+    /// Output modification time in milliseconds since the Unix epoch
+    #[serde(default)]
+    output_modified: Option<u64>,
+    original_size: u64,
+    #[serde(default)]
+    details: ImageDetails,
+    /// Seconds since the Unix epoch when the entry was recorded or last hit
+    #[serde(default)]
+    recorded_at: u64,
+}
+
+/// An output written by an earlier run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProducedOutput {
+    /// Fingerprint of the settings that wrote it
+    settings: String,
+    /// Seconds since the Unix epoch when it was written
+    recorded_at: u64,
+}
+
+/// On-disk representation of the cache.
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    encoder_version: String,
+    entries: HashMap<String, CacheEntry>,
+    /// Encoded outputs by content hash, independent of the encoder version
+    #[serde(default)]
+    produced_outputs: HashMap<String, ProducedOutput>,
+}
+
+/// Thread-safe result cache shared by every executor.
+pub struct ResultCache {
+    /// Location of the cache file; `None` keeps the cache in memory only.
+    path: Option<PathBuf>,
+    encoder_version: String,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    produced: Mutex<HashMap<String, ProducedOutput>>,
+    dirty: AtomicBool,
+}
+
+impl ResultCache {
+    /// Loads the cache from `path`, starting empty when the file is missing or
+    /// unreadable. Entries written by a different encoder version are dropped.
+    pub fn load(path: Option<PathBuf>, encoder_version: String) -> Self {
+        let file = path
+            .as_ref()
+            .and_then(|p| std::fs::read(p).ok())
+            .and_then(|bytes| match serde_json::from_slice::<CacheFile>(&bytes) {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    warn!("Ignoring unreadable optimization cache: {e}");
+                    None
+                }
+            });
+
+        let (entries, produced) = match file {
+            Some(file) if file.encoder_version == encoder_version => {
+                (file.entries, file.produced_outputs)
+            }
+            Some(file) => (HashMap::new(), file.produced_outputs),
+            None => Default::default(),
+        };
+
+        debug!(
+            "Optimization cache loaded ({} entries, {} known outputs)",
+            entries.len(),
+            produced.len()
+        );
+        Self {
+            path,
+            encoder_version,
+            entries: Mutex::new(entries),
+            produced: Mutex::new(produced),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// Derives the cache key for `task` given the SHA-256 of its input.
+    pub fn key(&self, task: &ImageTask, input_hash: &str) -> OptimizerResult<String> {
+        let settings = serde_json::to_string(&task.settings)
+            .map_err(|e| OptimizerError::processing(format!("Cannot serialize settings: {e}")))?;
+        let target = task.resolved_output_path()?;
+
+        let mut hasher = Sha256::new();
+        for part in [input_hash, &settings, &self.encoder_version, &target] {
+            hasher.update(part.as_bytes());
+            hasher.update([0u8]);
+        }
+        Ok(to_hex(&hasher.finalize()))
+    }
+
+    /// Returns a `cached` result when `key` is known and its output is intact.
+    ///
+    /// The output is trusted when its size and modification time still match;
+    /// otherwise it is hashed. Entries whose output vanished or no longer
+    /// matches the recorded hash are dropped so the task is regenerated.
+    pub fn lookup(&self, key: &str, task: &ImageTask) -> Option<OptimizationResult> {
+        let mut entry = self.lock().get(key).cloned()?;
+
+        let metadata = std::fs::metadata(&entry.output_path)
+            .ok()
+            .filter(|m| m.len() == entry.output_size);
+        let modified = metadata.as_ref().and_then(modified_ms);
+        let unchanged = modified.is_some() && modified == entry.output_modified;
+        let intact = metadata.is_some()
+            && (unchanged
+                || file_sha256(&entry.output_path).is_ok_and(|h| h == entry.output_hash));
+        if !intact {
+            debug!("Cached output for '{}' is missing or changed", extract_filename(&task.input_path));
+            self.lock().remove(key);
+            self.dirty.store(true, Ordering::Relaxed);
+            return None;
+        }
+
+        // A hit counts as recent use; a touched but identical output is
+        // remembered by its new time so the next lookup skips the hash
+        entry.recorded_at = unix_now();
+        if !unchanged {
+            entry.output_modified = modified;
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        self.lock().insert(key.to_string(), entry.clone());
+
+        let saved_bytes = entry.original_size as i64 - entry.output_size as i64;
+        let compression_ratio = if entry.original_size > 0 {
+            saved_bytes as f64 / entry.original_size as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        Some(OptimizationResult {
+            original_path: task.input_path.clone(),
+            optimized_path: entry.output_path,
+            original_size: entry.original_size,
+            optimized_size: entry.output_size,
+            success: true,
+            status: OptimizationStatus::Cached,
+            error: None,
+            error_code: None,
+            error_detail: None,
+            saved_bytes,
+            compression_ratio,
+            timings: PhaseTimings::default(),
+            details: entry.details,
+        })
+    }
+
+    /// Records a finished task under `key`.
+    ///
+    /// In-place outputs are also recorded under the key of their own content,
+    /// so the next run recognises the optimized file instead of re-encoding it.
+    pub fn record(&self, key: String, task: &ImageTask, result: &OptimizationResult) {
+        if !matches!(
+            result.status,
+            OptimizationStatus::Optimized | OptimizationStatus::CopiedOriginal
+        ) {
+            return;
+        }
+        let Ok(output_hash) = file_sha256(&result.optimized_path) else {
+            return;
+        };
+
+        let output_modified = std::fs::metadata(&result.optimized_path)
+            .ok()
+            .as_ref()
+            .and_then(modified_ms);
+
+        let entry = CacheEntry {
+            output_path: result.optimized_path.clone(),
+            output_hash: output_hash.clone(),
+            output_size: result.optimized_size,
+            output_modified,
+            original_size: result.original_size,
+            details: result.details.clone(),
+            recorded_at: unix_now(),
+        };
+
+        let in_place_key = (result.optimized_path == task.input_path)
+            .then(|| self.key(task, &output_hash).ok())
+            .flatten();
+
+        let mut entries = self.lock();
+        if let Some(in_place_key) = in_place_key {
+            entries.insert(in_place_key, entry.clone());
+        }
+        entries.insert(key, entry);
+        if entries.len() > MAX_ENTRIES {
+            forget_oldest(&mut entries, MAX_ENTRIES * 9 / 10, |e| e.recorded_at);
+        }
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Remembers an encoded output of `task` so it is recognised if it comes
+    /// back as input with the same settings.
+    ///
+    /// Copied originals are not recorded: they are the source itself.
+    pub fn remember_output(&self, task: &ImageTask, result: &OptimizationResult) {
+        if result.status != OptimizationStatus::Optimized {
+            return;
+        }
+        let Ok(hash) = file_sha256(&result.optimized_path) else {
+            return;
+        };
+        let Ok(settings) = settings_fingerprint(task) else {
+            return;
+        };
+
+        let mut produced = self.produced.lock().unwrap_or_else(|e| e.into_inner());
+        produced.insert(hash, ProducedOutput { settings, recorded_at: unix_now() });
+        if produced.len() > MAX_PRODUCED {
+            // Pruned with headroom so the sort is not repeated on every insert
+            forget_oldest(&mut produced, MAX_PRODUCED * 9 / 10, |o| o.recorded_at);
+        }
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether a file with this content hash was written by an earlier run
+    /// using the settings of `task`.
+    pub fn is_own_output(&self, hash: &str, task: &ImageTask) -> bool {
+        let Ok(settings) = settings_fingerprint(task) else {
+            return false;
+        };
+        self.produced
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(hash)
+            .is_some_and(|output| output.settings == settings)
+    }
+
+    /// Writes the cache to disk atomically if anything changed since the last flush.
+    pub fn flush(&self) -> OptimizerResult<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let file = CacheFile {
+            encoder_version: self.encoder_version.clone(),
+            entries: self.lock().clone(),
+            produced_outputs: self.produced.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        };
+        let json = serde_json::to_vec(&file)
+            .map_err(|e| OptimizerError::processing(format!("Cannot serialize cache: {e}")))?;
+
+        let staged = StagedOutput::new(path)?;
+        std::fs::write(staged.temp_path(), json)?;
+        staged.commit("", &BackupSettings::default())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, CacheEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// SHA-256 of the serialized settings of `task`.
+fn settings_fingerprint(task: &ImageTask) -> OptimizerResult<String> {
+    let settings = serde_json::to_vec(&task.settings)
+        .map_err(|e| OptimizerError::processing(format!("Cannot serialize settings: {e}")))?;
+    Ok(to_hex(&Sha256::digest(&settings)))
+}
+
+/// Drops the oldest items by `recorded_at` until `map` holds at most `keep`.
+fn forget_oldest<T>(map: &mut HashMap<String, T>, keep: usize, recorded_at: impl Fn(&T) -> u64) {
+    let mut by_age: Vec<(u64, String)> =
+        map.iter().map(|(key, item)| (recorded_at(item), key.clone())).collect();
+    by_age.sort_unstable();
+    let excess = map.len().saturating_sub(keep);
+    for (_, key) in by_age.into_iter().take(excess) {
+        map.remove(&key);
+    }
+}
+
+/// Modification time of a file in milliseconds since the Unix epoch.
+fn modified_ms(metadata: &Metadata) -> Option<u64> {
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(modified.as_millis()).ok()
+}
+
+/// Seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
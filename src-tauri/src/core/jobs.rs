@@ -0,0 +1,242 @@
+//! Registry of optimization jobs for the current session.
+//!
+//! Every batch becomes a [`Job`] wrapping its [`JobQueue`], so several jobs
+//! can run at once and be queried by id while (and after) they run. Jobs
+//! left unfinished by a previous session are registered as
+//! [`JobState::Interrupted`] until they are resumed or discarded.
+//!
+//! A running job can be paused: the executor checks the job's [`PauseGate`]
+//! between images, so the image in flight always finishes first.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::core::{JobQueue, OptimizationResult, QueueSummary};
+
+/// Finished jobs kept for `get_job_results`; older ones are forgotten first.
+const MAX_FINISHED_JOBS: usize = 20;
+
+/// Lifecycle of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+    /// Left unfinished by a previous session
+    Interrupted,
+    /// Tasks are being processed
+    Running,
+    /// Running, but held between images until resumed
+    Paused,
+    /// Every task has a result (individual tasks may still have failed)
+    Completed,
+    /// The job stopped early; its queue is kept so it can be resumed
+    Failed,
+}
+
+/// Snapshot of a job returned by `get_job_status` and `list_jobs`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+    #[serde(flatten)]
+    pub summary: QueueSummary,
+    pub state: JobState,
+    /// Why the job stopped early, for [`JobState::Failed`]
+    pub error: Option<String>,
+}
+
+/// Pause switch checked by the executor between images.
+pub struct PauseGate {
+    tx: watch::Sender<bool>,
+}
+
+impl Default for PauseGate {
+    fn default() -> Self {
+        Self { tx: watch::Sender::new(false) }
+    }
+}
+
+impl PauseGate {
+    /// Requests a pause. Returns `false` if already paused.
+    pub fn pause(&self) -> bool {
+        self.tx.send_if_modified(|paused| !std::mem::replace(paused, true))
+    }
+
+    /// Lifts a pause. Returns `false` if not paused.
+    pub fn resume(&self) -> bool {
+        self.tx.send_if_modified(|paused| std::mem::replace(paused, false))
+    }
+
+    /// Whether a pause is requested.
+    pub fn is_paused(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Waits until the gate is open; returns immediately when not paused.
+    pub async fn wait_resumed(&self) {
+        let mut rx = self.tx.subscribe();
+        // The sender lives as long as `self`, so this cannot fail.
+        let _ = rx.wait_for(|paused| !*paused).await;
+    }
+}
+
+/// One batch and its lifecycle state.
+pub struct Job {
+    queue: Arc<JobQueue>,
+    state: Mutex<(JobState, Option<String>)>,
+    pause: PauseGate,
+}
+
+impl Job {
+    fn new(queue: Arc<JobQueue>, state: JobState) -> Self {
+        Self {
+            queue,
+            state: Mutex::new((state, None)),
+            pause: PauseGate::default(),
+        }
+    }
+
+    /// Identifier returned to the frontend and included in progress events.
+    pub fn id(&self) -> &str {
+        self.queue.id()
+    }
+
+    /// Queue the executor consumes.
+    pub fn queue(&self) -> &JobQueue {
+        &self.queue
+    }
+
+    /// Gate the executor waits on between images.
+    pub fn pause_gate(&self) -> &PauseGate {
+        &self.pause
+    }
+
+    /// Current lifecycle state; a running job with a pending pause reports
+    /// [`JobState::Paused`].
+    pub fn state(&self) -> JobState {
+        match self.lock().0 {
+            JobState::Running if self.pause.is_paused() => JobState::Paused,
+            state => state,
+        }
+    }
+
+    /// Pauses a running job after its current image. Returns `false` when the
+    /// job is not running or already paused.
+    pub fn pause(&self) -> bool {
+        self.lock().0 == JobState::Running && self.pause.pause()
+    }
+
+    /// Lifts a pause. Returns `false` when the job was not paused.
+    pub fn unpause(&self) -> bool {
+        self.pause.resume()
+    }
+
+    /// Moves an interrupted or failed job back to running.
+    ///
+    /// Returns `false` when the job is already running or completed, so the
+    /// same queue is never consumed twice.
+    pub fn try_restart(&self) -> bool {
+        let mut state = self.lock();
+        if matches!(state.0, JobState::Interrupted | JobState::Failed) {
+            *state = (JobState::Running, None);
+            self.pause.resume();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Records how a run ended.
+    pub fn finish(&self, error: Option<String>) {
+        *self.lock() = match error {
+            None => (JobState::Completed, None),
+            Some(error) => (JobState::Failed, Some(error)),
+        };
+    }
+
+    /// Current progress and state.
+    pub fn status(&self) -> JobStatus {
+        let error = self.lock().1.clone();
+        let state = self.state();
+        JobStatus {
+            summary: self.queue.summary(),
+            state,
+            error,
+        }
+    }
+
+    /// Results of the tasks finished so far, in task order.
+    pub fn results(&self) -> Vec<OptimizationResult> {
+        self.queue.results()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, (JobState, Option<String>)> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// All jobs known to this session, keyed by id.
+#[derive(Default)]
+pub struct JobRegistry {
+    jobs: Mutex<HashMap<String, Arc<Job>>>,
+}
+
+impl JobRegistry {
+    /// Creates a registry holding the queues a previous session left unfinished.
+    pub fn with_interrupted(queues: Vec<JobQueue>) -> Self {
+        let registry = Self::default();
+        for queue in queues {
+            registry.insert(Arc::new(queue), JobState::Interrupted);
+        }
+        registry
+    }
+
+    /// Registers a queue as a new job in `state`.
+    pub fn insert(&self, queue: Arc<JobQueue>, state: JobState) -> Arc<Job> {
+        let job = Arc::new(Job::new(queue, state));
+        let mut jobs = self.lock();
+        jobs.insert(job.id().to_string(), job.clone());
+        prune_finished(&mut jobs);
+        job
+    }
+
+    /// Looks up a job by id.
+    pub fn get(&self, id: &str) -> Option<Arc<Job>> {
+        self.lock().get(id).cloned()
+    }
+
+    /// Removes a job from the registry.
+    pub fn remove(&self, id: &str) -> Option<Arc<Job>> {
+        self.lock().remove(id)
+    }
+
+    /// Status of every job, oldest first.
+    pub fn list(&self) -> Vec<JobStatus> {
+        let mut statuses: Vec<_> = self.lock().values().map(|job| job.status()).collect();
+        statuses.sort_by(|a, b| a.summary.id.cmp(&b.summary.id));
+        statuses
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<Job>>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Drops the oldest completed jobs beyond [`MAX_FINISHED_JOBS`].
+///
+/// Ids start with their creation timestamp, so sorting by id sorts by age.
+fn prune_finished(jobs: &mut HashMap<String, Arc<Job>>) {
+    let mut finished: Vec<String> = jobs
+        .values()
+        .filter(|job| job.state() == JobState::Completed)
+        .map(|job| job.id().to_string())
+        .collect();
+    if finished.len() <= MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort();
+    for id in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+        jobs.remove(id);
+    }
+}
@@ -0,0 +1,109 @@
+//! Per-image resource limits against decompression bombs.
+//!
+//! A small file can declare an enormous canvas (a 60000×60000 PNG
+//! compresses to a few megabytes but decodes to over 13 GB). Limits are
+//! checked against the file size and the image header, before any pixels
+//! are decoded, and a breach fails the image with a
+//! [`LimitExceeded`](crate::utils::OptimizerError::LimitExceeded) error.
+//!
+//! Every image limit is unlimited by default, so any input libvips can open
+//! is processed; tighter limits are opted into with `set_limits`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::error::{ErrorCode, ValidationError};
+use crate::utils::{OptimizerError, OptimizerResult};
+
+/// Resource limits applied to every image, plus the libvips cache caps.
+///
+/// Missing fields deserialize to their defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResourceLimits {
+    /// Width × height of one page
+    pub max_pixels: u64,
+    /// Largest accepted width or height
+    pub max_dimension: u32,
+    /// Largest accepted input file, in bytes
+    pub max_file_size: u64,
+    /// Most animation frames loaded at once; stills only load their first page
+    pub max_pages: u32,
+    /// Memory the libvips operation cache may hold, in bytes
+    pub cache_max_mem: u64,
+    /// Files the libvips operation cache may keep open
+    pub cache_max_files: u32,
+    /// Memory all images being decoded may use together, in bytes
+    pub max_memory: u64,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            max_pixels: u64::MAX,
+            max_dimension: u32::MAX,
+            max_file_size: u64::MAX,
+            max_pages: u32::MAX,
+            // libvips' own defaults
+            cache_max_mem: 100 * 1024 * 1024,
+            cache_max_files: 100,
+            max_memory: 2 * 1024 * 1024 * 1024,
+        }
+    }
+}
+
+impl ResourceLimits {
+    /// Rejects zero limits, which would fail every image.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.max_pixels == 0
+            || self.max_dimension == 0
+            || self.max_file_size == 0
+            || self.max_pages == 0
+            || self.max_memory == 0
+        {
+            return Err(ValidationError::settings("Resource limits must be greater than 0"));
+        }
+        Ok(())
+    }
+
+    /// Checks the input file size.
+    pub fn check_file_size(&self, size: u64) -> OptimizerResult<()> {
+        if size > self.max_file_size {
+            return Err(exceeded(format!(
+                "File is {} MiB, over the {} MiB limit",
+                size >> 20,
+                self.max_file_size >> 20
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks the dimensions read from an image header and the number of
+    /// pages that will be decoded.
+    pub fn check_header(&self, width: u32, height: u32, pages: u32) -> OptimizerResult<()> {
+        if width.max(height) > self.max_dimension {
+            return Err(exceeded(format!(
+                "Image is {width}×{height}, over the {} px dimension limit",
+                self.max_dimension
+            )));
+        }
+        let pixels = width as u64 * height as u64;
+        if pixels > self.max_pixels {
+            return Err(exceeded(format!(
+                "Image has {} megapixels, over the {} megapixel limit",
+                pixels / 1_000_000,
+                self.max_pixels / 1_000_000
+            )));
+        }
+        if pages > self.max_pages {
+            return Err(exceeded(format!(
+                "Image has {pages} pages, over the {} page limit",
+                self.max_pages
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn exceeded(message: String) -> OptimizerError {
+    OptimizerError::new(ErrorCode::TooLarge, message).with_operation("limits")
+}
@@ -0,0 +1,135 @@
+//! Memory-bounded dispatch of images.
+//!
+//! Every executor shares one [`MemoryBudget`]. Before an image is decoded it
+//! reserves its estimated decoded size (from the pre-flight header probe);
+//! dispatch waits while the reservations of other running images, or the
+//! memory libvips actually tracks, would push past the ceiling. An image
+//! larger than the whole ceiling still runs, but only once nothing else
+//! does, so a single huge scan is never starved.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tracing::debug;
+
+use super::state::VipsAppGuard;
+
+/// How often a waiting image re-reads libvips' tracked memory, which
+/// changes without any reservation being released.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Process-wide ceiling on the memory images may use while they are decoded.
+pub struct MemoryBudget {
+    vips: Arc<VipsAppGuard>,
+    ceiling: AtomicU64,
+    /// Bytes reserved by images currently being processed.
+    reserved: Mutex<u64>,
+    released: Notify,
+}
+
+/// Memory held for one image; released on drop.
+pub struct Reservation {
+    budget: Arc<MemoryBudget>,
+    bytes: u64,
+}
+
+impl MemoryBudget {
+    pub(super) fn new(vips: Arc<VipsAppGuard>, ceiling: u64) -> Self {
+        Self {
+            vips,
+            ceiling: AtomicU64::new(ceiling),
+            reserved: Mutex::new(0),
+            released: Notify::new(),
+        }
+    }
+
+    /// Changes the ceiling; waiting images are re-checked against it.
+    pub fn set_ceiling(&self, ceiling: u64) {
+        self.ceiling.store(ceiling, Ordering::Relaxed);
+        self.released.notify_waiters();
+    }
+
+    pub fn ceiling(&self) -> u64 {
+        self.ceiling.load(Ordering::Relaxed)
+    }
+
+    /// Bytes libvips currently has allocated for pixel buffers and its cache.
+    pub fn in_use(&self) -> u64 {
+        self.vips.0.tracked_get_mem()
+    }
+
+    /// Most bytes libvips has had allocated at once since startup.
+    pub fn peak(&self) -> u64 {
+        self.vips.0.tracked_get_mem_highwater()
+    }
+
+    /// Waits until `estimate` bytes fit under the ceiling, then holds them
+    /// until the returned [`Reservation`] is dropped.
+    pub async fn reserve(self: &Arc<Self>, estimate: u64) -> Reservation {
+        loop {
+            // Registered before checking so a release in between is not missed.
+            let released = self.released.notified();
+            if self.try_reserve(estimate) {
+                return Reservation { budget: self.clone(), bytes: estimate };
+            }
+            let _ = tokio::time::timeout(POLL_INTERVAL, released).await;
+        }
+    }
+
+    fn try_reserve(&self, estimate: u64) -> bool {
+        let mut reserved = self.reserved.lock().unwrap_or_else(|e| e.into_inner());
+        let ceiling = self.ceiling();
+        if *reserved == 0 {
+            if self.in_use().saturating_add(estimate) > ceiling {
+                // Nothing is running, so what libvips holds is its cache.
+                debug!("Dropping the libvips cache to fit a {} MiB image", estimate >> 20);
+                unsafe { libvips::bindings::vips_cache_drop_all() };
+            }
+            *reserved = estimate;
+            return true;
+        }
+        let used = (*reserved).max(self.in_use());
+        if used.saturating_add(estimate) > ceiling {
+            return false;
+        }
+        *reserved += estimate;
+        true
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let mut reserved = self.budget.reserved.lock().unwrap_or_else(|e| e.into_inner());
+        *reserved = reserved.saturating_sub(self.bytes);
+        drop(reserved);
+        self.budget.released.notify_waiters();
+    }
+}
+
+/// Estimated decoded size of each pending task, from the pre-flight probes.
+///
+/// Tasks without an estimate (SVGs, or tasks added after the probe) count
+/// as 0 bytes and are only throttled by libvips' tracked memory.
+#[derive(Debug, Default)]
+pub struct MemoryPlan {
+    estimates: HashMap<usize, u64>,
+}
+
+impl MemoryPlan {
+    pub fn insert(&mut self, index: usize, bytes: u64) {
+        self.estimates.insert(index, bytes);
+    }
+
+    /// Estimated decoded bytes of task `index`.
+    pub fn estimate(&self, index: usize) -> u64 {
+        self.estimates.get(&index).copied().unwrap_or(0)
+    }
+
+    /// Sum of all estimates.
+    pub fn total(&self) -> u64 {
+        self.estimates.values().sum()
+    }
+}
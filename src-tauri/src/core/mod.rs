@@ -2,13 +2,34 @@
 //!
 //! This module contains the fundamental types used throughout the application:
 //! - [`AppState`]: Application state managed by Tauri
+//! - [`ResultCache`]: Persistent cache of finished optimizations
+//! - [`JobQueue`]: Durable task queue that survives restarts
+//! - [`JobRegistry`]: Jobs of the current session, queried by id
+//! - [`ResourceLimits`]: Per-image limits checked before decoding
+//! - [`MemoryBudget`]: Memory ceiling shared by every executor
 //! - [`ImageTask`]: Represents an image optimization task
 //! - [`ImageSettings`]: Configuration for image processing
 //! - [`OptimizationResult`]: Result of an optimization operation
+mod cache;
+mod jobs;
+mod limits;
+mod memory;
+mod queue;
 mod state;
 mod types;
 mod task;
 
+pub use cache::{CACHE_FILE_NAME, ResultCache};
+pub use jobs::{Job, JobRegistry, JobState, JobStatus, PauseGate};
+pub use limits::ResourceLimits;
+pub use memory::{MemoryBudget, MemoryPlan};
+pub use queue::{JobQueue, QueueSummary, TaskState};
 pub use state::AppState;
-pub use types::{ImageSettings, QualitySettings, ResizeSettings, OptimizationResult};
+pub use types::{
+    AdvancedSettings, AlphaPolicy, AlphaSettings, AutoFormatSettings, AvifAdvanced, AvifEncoder,
+    BackupMode, BackupSettings, ChromaSubsampling, CollisionPolicy, Compression,
+    DirectoryOptions, FormatCandidate, ImageDetails, ImageSettings, JpegAdvanced, PngAdvanced,
+    PngFilter, PhaseTimings, Priority, QualitySettings, ResizeSettings, SizePolicy,
+    SymlinkPolicy, OptimizationResult, OptimizationStatus, WatchConfig, WebpAdvanced, WebpPreset,
+};
 pub use task::ImageTask;
\ No newline at end of file
@@ -0,0 +1,412 @@
+//! Durable job queue so large batches survive crashes and restarts.
+//!
+//! A persisted queue is two files in the jobs directory: `<id>.json`, the
+//! task list written once when the job is created, and `<id>.journal`, one
+//! JSON line per finished task appended as the executor works. Replaying the
+//! journal tells which tasks are done, which failed, and which are still
+//! pending, without rewriting the task list after every image.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::core::{BackupSettings, ImageTask, OptimizationResult, OptimizationStatus};
+use crate::processing::output::StagedOutput;
+use crate::utils::{OptimizerError, OptimizerResult};
+
+/// Disambiguates queues created within the same millisecond.
+static QUEUE_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Where a task stands within its queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskState {
+    /// Not processed yet, or interrupted mid-task
+    Pending,
+    /// Finished with any non-failed status
+    Done,
+    /// Finished with an error
+    Failed,
+}
+
+/// Counts for a queue, shown to the user when offering to resume.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueSummary {
+    pub id: String,
+    /// Local time the job was created, RFC 3339
+    pub created_at: String,
+    pub total: usize,
+    pub pending: usize,
+    pub done: usize,
+    pub failed: usize,
+}
+
+/// Task list written once at creation.
+#[derive(Serialize, Deserialize)]
+struct QueueHeader {
+    id: String,
+    created_at: String,
+    tasks: Vec<ImageTask>,
+}
+
+/// One journal line.
+#[derive(Serialize, Deserialize)]
+struct JournalRecord {
+    index: usize,
+    result: OptimizationResult,
+}
+
+/// Mutable part of a queue.
+struct QueueProgress {
+    results: Vec<Option<OptimizationResult>>,
+    /// Every task before this index has a result.
+    cursor: usize,
+    journal: Option<File>,
+}
+
+/// Ordered tasks of one job plus the outcome of each finished task.
+pub struct JobQueue {
+    id: String,
+    created_at: String,
+    tasks: Vec<ImageTask>,
+    /// Header and journal paths; `None` for queues kept in memory only.
+    files: Option<(PathBuf, PathBuf)>,
+    progress: Mutex<QueueProgress>,
+}
+
+impl JobQueue {
+    /// Creates a queue persisted in `dir`, or in memory when `dir` is `None`.
+    pub fn create(dir: Option<&Path>, tasks: Vec<ImageTask>) -> OptimizerResult<Self> {
+        let now = chrono::Local::now();
+        let id = format!(
+            "{}-{}",
+            now.format("%Y%m%d-%H%M%S%3f"),
+            QUEUE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let header = QueueHeader {
+            id,
+            created_at: now.to_rfc3339(),
+            tasks,
+        };
+
+        let files = match dir {
+            Some(dir) => {
+                let (header_path, journal_path) = queue_files(dir, &header.id);
+                let json = serde_json::to_vec(&header)
+                    .map_err(|e| OptimizerError::processing(format!("Cannot serialize job queue: {e}")))?;
+                let staged = StagedOutput::new(&header_path)?;
+                std::fs::write(staged.temp_path(), json)?;
+                staged.commit("", &BackupSettings::default())?;
+                Some((header_path, journal_path))
+            }
+            None => None,
+        };
+
+        let results = vec![None; header.tasks.len()];
+        Self::from_parts(header, files, results)
+    }
+
+    /// Reopens a persisted queue from its header file and replays its journal.
+    ///
+    /// A torn final journal line (crash mid-append) is cut off before the
+    /// journal is reopened for appending, so the next record starts on a line
+    /// of its own; that task simply stays pending. Unreadable lines elsewhere
+    /// are skipped rather than ending the replay.
+    pub fn open(header_path: &Path) -> OptimizerResult<Self> {
+        let bytes = std::fs::read(header_path)?;
+        let header: QueueHeader = serde_json::from_slice(&bytes)
+            .map_err(|e| OptimizerError::processing(format!("Corrupt job queue: {e}")))?;
+        let journal_path = header_path.with_extension("journal");
+
+        let mut results = vec![None; header.tasks.len()];
+        if let Ok(journal) = std::fs::read(&journal_path) {
+            let complete = journal.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+            for line in journal[..complete].split(|&b| b == b'\n').filter(|l| !l.is_empty()) {
+                match serde_json::from_slice::<JournalRecord>(line) {
+                    Ok(record) if record.index < results.len() => {
+                        results[record.index] = Some(record.result);
+                    }
+                    Ok(_) => {}
+                    Err(e) => debug!("Skipping unreadable journal line of {}: {e}", header.id),
+                }
+            }
+            if complete < journal.len() {
+                debug!("Cutting torn journal line of {}", header.id);
+                let truncated = OpenOptions::new()
+                    .write(true)
+                    .open(&journal_path)
+                    .and_then(|file| file.set_len(complete as u64));
+                if let Err(e) = truncated {
+                    warn!("Failed to repair journal of job {}: {e}", header.id);
+                }
+            }
+        }
+
+        Self::from_parts(header, Some((header_path.to_path_buf(), journal_path)), results)
+    }
+
+    /// Opens every queue in `dir` that still has pending tasks.
+    ///
+    /// Fully processed queues left behind by an interrupted cleanup are removed.
+    pub fn load_unfinished(dir: &Path) -> Vec<JobQueue> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+
+        let mut queues = Vec::new();
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            match JobQueue::open(&path) {
+                Ok(queue) if queue.is_drained() => queue.remove_files(),
+                Ok(queue) => queues.push(queue),
+                Err(e) => warn!("Ignoring job queue {}: {e}", path.display()),
+            }
+        }
+        queues.sort_by(|a, b| a.id.cmp(&b.id));
+        queues
+    }
+
+    fn from_parts(
+        header: QueueHeader,
+        files: Option<(PathBuf, PathBuf)>,
+        results: Vec<Option<OptimizationResult>>,
+    ) -> OptimizerResult<Self> {
+        let journal = match &files {
+            Some((_, journal_path)) => Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(journal_path)
+                    .map_err(|e| OptimizerError::io("Cannot open job journal", &e))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            id: header.id,
+            created_at: header.created_at,
+            tasks: header.tasks,
+            files,
+            progress: Mutex::new(QueueProgress { results, cursor: 0, journal }),
+        })
+    }
+
+    /// Identifier of this queue.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Total number of tasks.
+    pub fn total(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Hands out the first task without a result, or `None` when every task
+    /// has one.
+    ///
+    /// Tasks are handed out one at a time: a task that never got a result,
+    /// because its run stopped mid-task, is handed out again by the next call,
+    /// so a restarted job picks it up.
+    pub fn next_pending(&self) -> Option<(usize, ImageTask)> {
+        let mut progress = self.lock();
+        while progress.cursor < self.tasks.len() {
+            let index = progress.cursor;
+            if progress.results[index].is_none() {
+                return Some((index, self.tasks[index].clone()));
+            }
+            progress.cursor += 1;
+        }
+        None
+    }
+
+    /// Every task without a result, with its index.
+    pub fn pending(&self) -> Vec<(usize, ImageTask)> {
+        let progress = self.lock();
+        progress
+            .results
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.is_none())
+            .map(|(index, _)| (index, self.tasks[index].clone()))
+            .collect()
+    }
+
+    /// Records the outcome of task `index` and appends it to the journal.
+    ///
+    /// A failed journal write is logged, not propagated: the result is still
+    /// returned to the caller, and at worst the task is redone on resume.
+    pub fn complete(&self, index: usize, result: &OptimizationResult) {
+        let mut progress = self.lock();
+        if let Some(journal) = progress.journal.as_mut() {
+            let record = JournalRecord { index, result: result.clone() };
+            let written = serde_json::to_string(&record)
+                .map_err(std::io::Error::other)
+                .and_then(|line| writeln!(journal, "{line}"))
+                .and_then(|_| journal.sync_data());
+            if let Err(e) = written {
+                warn!("Failed to journal task {index} of job {}: {e}", self.id);
+            }
+        }
+        progress.results[index] = Some(result.clone());
+    }
+
+    /// Number of tasks with a result.
+    pub fn finished(&self) -> usize {
+        self.lock().results.iter().filter(|r| r.is_some()).count()
+    }
+
+    /// Whether every task has a result.
+    pub fn is_drained(&self) -> bool {
+        self.lock().results.iter().all(Option::is_some)
+    }
+
+    /// State of every task, in queue order.
+    pub fn states(&self) -> Vec<TaskState> {
+        self.lock()
+            .results
+            .iter()
+            .map(|r| match r {
+                None => TaskState::Pending,
+                Some(r) if r.status == OptimizationStatus::Failed => TaskState::Failed,
+                Some(_) => TaskState::Done,
+            })
+            .collect()
+    }
+
+    /// Pending/done/failed counts.
+    pub fn summary(&self) -> QueueSummary {
+        let states = self.states();
+        let count = |state| states.iter().filter(|s| **s == state).count();
+        QueueSummary {
+            id: self.id.clone(),
+            created_at: self.created_at.clone(),
+            total: states.len(),
+            pending: count(TaskState::Pending),
+            done: count(TaskState::Done),
+            failed: count(TaskState::Failed),
+        }
+    }
+
+    /// Results of all finished tasks, in queue order.
+    pub fn results(&self) -> Vec<OptimizationResult> {
+        self.lock().results.iter().flatten().cloned().collect()
+    }
+
+    /// Deletes the queue's files once the job no longer needs to be resumable.
+    pub fn remove_files(&self) {
+        let Some((header_path, journal_path)) = &self.files else {
+            return;
+        };
+        // Close the journal before removing it (required on Windows).
+        self.lock().journal = None;
+        for path in [header_path, journal_path] {
+            if let Err(e) = std::fs::remove_file(path)
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                warn!("Failed to remove {}: {e}", path.display());
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueProgress> {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Header and journal paths for queue `id` in `dir`.
+fn queue_files(dir: &Path, id: &str) -> (PathBuf, PathBuf) {
+    (dir.join(format!("{id}.json")), dir.join(format!("{id}.journal")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{ImageDetails, PhaseTimings};
+
+    fn tasks(count: usize) -> Vec<ImageTask> {
+        (0..count)
+            .map(|i| {
+                serde_json::from_value(serde_json::json!({
+                    "input_path": format!("image-{i}.png"),
+                    "settings": {
+                        "quality": { "global": 80 },
+                        "resize": { "maintainAspect": true, "mode": "none" },
+                        "outputFormat": "original",
+                    },
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    fn failed(path: &str) -> OptimizationResult {
+        OptimizationResult {
+            original_path: path.to_string(),
+            optimized_path: path.to_string(),
+            original_size: 0,
+            optimized_size: 0,
+            success: false,
+            status: OptimizationStatus::Failed,
+            error: Some("failed".to_string()),
+            error_code: None,
+            error_detail: None,
+            saved_bytes: 0,
+            compression_ratio: 0.0,
+            timings: PhaseTimings::default(),
+            details: ImageDetails::default(),
+        }
+    }
+
+    #[test]
+    fn restart_hands_out_the_interrupted_task_again() {
+        let queue = JobQueue::create(None, tasks(3)).unwrap();
+        let (first, _) = queue.next_pending().unwrap();
+        queue.complete(first, &failed("image-0.png"));
+
+        // The run stops while the second task is in flight
+        let (interrupted, _) = queue.next_pending().unwrap();
+        assert_eq!(interrupted, 1);
+
+        let mut handed_out = Vec::new();
+        while let Some((index, _)) = queue.next_pending() {
+            queue.complete(index, &failed("image.png"));
+            handed_out.push(index);
+        }
+        assert_eq!(handed_out, [1, 2]);
+        assert!(queue.is_drained());
+    }
+
+    #[test]
+    fn torn_journal_line_does_not_swallow_later_records() {
+        let dir = std::env::temp_dir().join(format!("queue-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let queue = JobQueue::create(Some(&dir), tasks(3)).unwrap();
+        let (header_path, journal_path) = queue_files(&dir, queue.id());
+        queue.complete(0, &failed("image-0.png"));
+        drop(queue);
+
+        // A crash mid-append leaves half a record behind
+        let mut journal = OpenOptions::new().append(true).open(&journal_path).unwrap();
+        journal.write_all(b"{\"index\":1,\"resu").unwrap();
+        drop(journal);
+
+        let queue = JobQueue::open(&header_path).unwrap();
+        assert_eq!(queue.finished(), 1);
+        queue.complete(1, &failed("image-1.png"));
+        queue.complete(2, &failed("image-2.png"));
+        drop(queue);
+
+        let queue = JobQueue::open(&header_path).unwrap();
+        assert!(queue.is_drained());
+        queue.remove_files();
+        let _ = std::fs::remove_dir(&dir);
+    }
+}
@@ -1,8 +1,24 @@
 //! Application state management for Tauri.
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+use crate::core::{
+    CACHE_FILE_NAME, ImageTask, Job, JobQueue, JobRegistry, JobState, JobStatus, MemoryBudget,
+    Priority, QueueSummary, ResourceLimits, ResultCache, WatchConfig,
+};
 use crate::processing::libvips::NativeExecutor;
-use tracing::debug;
+use crate::processing::watch::FolderWatch;
+use crate::utils::OptimizerResult;
+use crate::utils::error::ValidationError;
+use tracing::{debug, info};
+
+/// Directory below the app data dir that holds persisted job queues.
+const JOBS_DIR_NAME: &str = "jobs";
+
+/// libvips worker threads in [`Priority::Background`] mode.
+const BACKGROUND_CONCURRENCY: i32 = 1;
 
 /// Thread-safe guard for the libvips `VipsApp` lifecycle.
 ///
@@ -13,7 +29,7 @@ use tracing::debug;
 /// # Safety
 /// libvips is designed for concurrent multi-threaded use. Operations on
 /// separate `VipsImage` instances from different threads are safe.
-struct VipsAppGuard(libvips::VipsApp);
+pub(super) struct VipsAppGuard(pub(super) libvips::VipsApp);
 
 // libvips is designed for concurrent use; individual VipsImage instances must
 // not be shared between threads, but concurrent creation on separate threads is safe.
@@ -22,13 +38,28 @@ unsafe impl Sync for VipsAppGuard {}
 
 /// Application state managed by Tauri.
 ///
-/// Holds the app handle and keeps the libvips runtime alive for the
-/// entire application lifetime.
+/// Holds the app handle, the result cache, the job registry, the processing
+/// priority, the resource limits and memory budget, the active folder watches,
+/// and keeps the libvips runtime alive for the entire application lifetime.
 #[derive(Clone)]
 pub struct AppState {
     app_handle: Arc<tauri::AppHandle>,
+    /// Results of earlier runs, shared by every executor.
+    cache: Arc<ResultCache>,
+    /// Where job queues are persisted; `None` when the app data dir is unavailable.
+    jobs_dir: Option<PathBuf>,
+    /// Jobs of this session, plus those a previous session left unfinished.
+    jobs: Arc<JobRegistry>,
+    /// Active folder watches keyed by the directory path they were started with.
+    watches: Arc<Mutex<HashMap<String, FolderWatch>>>,
+    /// Current processing priority.
+    priority: Arc<Mutex<Priority>>,
+    /// Per-image limits, shared with every executor so changes apply to running jobs.
+    limits: Arc<Mutex<ResourceLimits>>,
+    /// Memory ceiling shared by every executor, so concurrent jobs stay under it together.
+    budget: Arc<MemoryBudget>,
     /// Keeps libvips initialized until the last AppState clone is dropped.
-    _vips: Arc<VipsAppGuard>,
+    vips: Arc<VipsAppGuard>,
 }
 
 impl AppState {
@@ -42,15 +73,168 @@ impl AppState {
         // 0 = let libvips decide based on available CPU cores
         vips.concurrency_set(0);
         debug!("libvips initialized (concurrency: {})", vips.concurency_get());
+        let limits = ResourceLimits::default();
+        apply_cache_caps(&vips, &limits);
+
+        // Outputs from a different encoder build are not reused.
+        let encoder_version = format!(
+            "{}+libvips-{}",
+            env!("CARGO_PKG_VERSION"),
+            vips.version_string().unwrap_or("unknown")
+        );
+        let data_dir = app.path().app_data_dir().ok();
+        let cache_path = data_dir.as_ref().map(|dir| dir.join(CACHE_FILE_NAME));
+        let cache = ResultCache::load(cache_path, encoder_version);
+
+        let jobs_dir = data_dir.map(|dir| dir.join(JOBS_DIR_NAME));
+        let interrupted = jobs_dir
+            .as_deref()
+            .map(JobQueue::load_unfinished)
+            .unwrap_or_default();
+        if !interrupted.is_empty() {
+            info!("{} interrupted job(s) can be resumed", interrupted.len());
+        }
 
+        let vips = Arc::new(VipsAppGuard(vips));
         Self {
             app_handle: Arc::new(app),
-            _vips: Arc::new(VipsAppGuard(vips)),
+            cache: Arc::new(cache),
+            jobs_dir,
+            jobs: Arc::new(JobRegistry::with_interrupted(interrupted)),
+            watches: Arc::new(Mutex::new(HashMap::new())),
+            priority: Arc::new(Mutex::new(Priority::Normal)),
+            limits: Arc::new(Mutex::new(limits)),
+            budget: Arc::new(MemoryBudget::new(vips.clone(), limits.max_memory)),
+            vips,
         }
     }
 
     /// Creates a new native libvips executor for batch processing.
     pub fn create_executor(&self) -> NativeExecutor {
-        NativeExecutor::new((*self.app_handle).clone())
+        NativeExecutor::new(
+            (*self.app_handle).clone(),
+            self.cache.clone(),
+            self.limits.clone(),
+            self.budget.clone(),
+        )
     }
-} 
\ No newline at end of file
+
+    /// Registers a running job for `tasks`, persisted so it can be resumed after a crash.
+    pub fn create_job(&self, tasks: Vec<ImageTask>) -> OptimizerResult<Arc<Job>> {
+        let queue = JobQueue::create(self.jobs_dir.as_deref(), tasks)?;
+        Ok(self.jobs.insert(Arc::new(queue), JobState::Running))
+    }
+
+    /// Looks up a job by id.
+    pub fn job(&self, id: &str) -> Option<Arc<Job>> {
+        self.jobs.get(id)
+    }
+
+    /// Status of every job in the registry.
+    pub fn list_jobs(&self) -> Vec<JobStatus> {
+        self.jobs.list()
+    }
+
+    /// Summaries of the jobs a previous session left unfinished.
+    pub fn resumable_jobs(&self) -> Vec<QueueSummary> {
+        self.jobs
+            .list()
+            .into_iter()
+            .filter(|status| status.state == JobState::Interrupted)
+            .map(|status| status.summary)
+            .collect()
+    }
+
+    /// Forgets an interrupted or failed job and deletes its queue.
+    /// Returns `false` for unknown, running, or completed jobs.
+    pub fn discard_job(&self, id: &str) -> bool {
+        let discardable = self
+            .jobs
+            .get(id)
+            .is_some_and(|job| matches!(job.state(), JobState::Interrupted | JobState::Failed));
+        if !discardable {
+            return false;
+        }
+        match self.jobs.remove(id) {
+            Some(job) => {
+                job.queue().remove_files();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Switches processing priority by changing libvips' worker thread count.
+    ///
+    /// Takes effect for images started after the call; the image in flight
+    /// keeps its threads.
+    pub fn set_priority(&self, priority: Priority) {
+        let mut current = self.priority.lock().unwrap_or_else(|e| e.into_inner());
+        let concurrency = match priority {
+            // 0 = let libvips decide based on available CPU cores
+            Priority::Normal => 0,
+            Priority::Background => BACKGROUND_CONCURRENCY,
+        };
+        self.vips.0.concurrency_set(concurrency);
+        *current = priority;
+        debug!(
+            "Priority set to {:?} (libvips concurrency: {})",
+            priority,
+            self.vips.0.concurency_get()
+        );
+    }
+
+    /// Current processing priority.
+    pub fn priority(&self) -> Priority {
+        *self.priority.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Replaces the resource limits and applies the libvips cache caps.
+    ///
+    /// Per-image limits apply to images started after the call.
+    pub fn set_limits(&self, limits: ResourceLimits) -> OptimizerResult<()> {
+        limits.validate()?;
+        let mut current = self.limits.lock().unwrap_or_else(|e| e.into_inner());
+        apply_cache_caps(&self.vips.0, &limits);
+        self.budget.set_ceiling(limits.max_memory);
+        *current = limits;
+        debug!("Resource limits set to {:?}", limits);
+        Ok(())
+    }
+
+    /// Current resource limits.
+    pub fn limits(&self) -> ResourceLimits {
+        *self.limits.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Starts watching `config.path`; fails if that directory is already watched.
+    pub fn start_watch(&self, config: WatchConfig) -> OptimizerResult<()> {
+        let mut watches = self.watches.lock().unwrap_or_else(|e| e.into_inner());
+        if watches.contains_key(&config.path) {
+            return Err(ValidationError::settings(format!("Already watching {}", config.path)).into());
+        }
+        let path = config.path.clone();
+        let watch = FolderWatch::start(config, self.create_executor())?;
+        watches.insert(path, watch);
+        Ok(())
+    }
+
+    /// Stops the watch on `path`. Returns `false` when nothing was watching it.
+    pub fn stop_watch(&self, path: &str) -> bool {
+        let mut watches = self.watches.lock().unwrap_or_else(|e| e.into_inner());
+        watches.remove(path).is_some()
+    }
+
+    /// Configurations of all active watches.
+    pub fn watch_configs(&self) -> Vec<WatchConfig> {
+        let watches = self.watches.lock().unwrap_or_else(|e| e.into_inner());
+        watches.values().map(|w| w.config().clone()).collect()
+    }
+}
+
+/// Caps the libvips operation cache, which otherwise holds recent
+/// intermediate images and open files.
+fn apply_cache_caps(vips: &libvips::VipsApp, limits: &ResourceLimits) {
+    vips.cache_set_max_mem(limits.cache_max_mem);
+    vips.cache_set_max_files(limits.cache_max_files.min(i32::MAX as u32) as i32);
+}
@@ -1,7 +1,10 @@
 //! Image task definition and creation.
 
+use std::path::Path;
 use serde::{Deserialize, Serialize};
-use crate::core::ImageSettings;
+use crate::core::{DirectoryOptions, ImageSettings};
+use crate::processing::libvips::target_format;
+use crate::utils::{ImageFormat, OptimizerResult, detect_format, ensure_correct_extension};
 
 /// Represents a single image optimization task.
 ///
@@ -10,8 +13,67 @@ use crate::core::ImageSettings;
 pub struct ImageTask {
     /// Path to the source image file
     pub input_path: String,
-    /// Path where the optimized image will be written
+    /// Path where the optimized image will be written; empty when the
+    /// backend should derive it from the output template
+    #[serde(default)]
     pub output_path: String,
     /// Optimization settings (quality, resize, format)
     pub settings: ImageSettings,
-} 
\ No newline at end of file
+}
+
+impl ImageTask {
+    /// Builds a task for `image` found while scanning `root`.
+    ///
+    /// With an output root (and no explicit template) the image's path
+    /// relative to `root` is mirrored into it. Otherwise the output path is
+    /// left empty for the output template, which sees `root` as `sourceRoot`.
+    pub fn from_directory_entry(
+        root: &Path,
+        image: &Path,
+        settings: &ImageSettings,
+        options: &DirectoryOptions,
+    ) -> Self {
+        let mut settings = settings.clone();
+        settings.source_root = Some(root.to_string_lossy().to_string());
+
+        let output_path = match (&options.output_root, &settings.output_template) {
+            (Some(out), None) => {
+                let rel = image.strip_prefix(root).unwrap_or(image);
+                Path::new(out).join(rel).to_string_lossy().to_string()
+            }
+            _ => String::new(),
+        };
+
+        Self {
+            input_path: image.to_string_lossy().to_string(),
+            output_path,
+            settings,
+        }
+    }
+
+    /// Path the output should be written to before extension correction.
+    ///
+    /// In-place tasks target their own input; the executor stages the write
+    /// in a temp file so the original is only replaced once encoding succeeds.
+    /// When an in-place task converts formats, the converted file lands next
+    /// to the original and the original is left untouched.
+    pub fn requested_output_path(&self) -> &str {
+        if self.settings.in_place {
+            &self.input_path
+        } else {
+            &self.output_path
+        }
+    }
+
+    /// Final output path after format conversion corrects the extension.
+    ///
+    /// SVG tasks are never converted, so their requested path is final.
+    pub fn resolved_output_path(&self) -> OptimizerResult<String> {
+        let requested = self.requested_output_path();
+        if detect_format(&self.input_path)?.format == ImageFormat::SVG {
+            return Ok(requested.to_string());
+        }
+        let format = target_format(&self.input_path, &self.settings)?;
+        Ok(ensure_correct_extension(requested, &self.input_path, &format))
+    }
+}
@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::utils::error::ErrorCode;
+
 /// Configuration settings for image optimization.
 ///
 /// Contains quality, resize, and output format settings that control
@@ -12,9 +14,242 @@ pub struct ImageSettings {
     pub quality: QualitySettings,
     /// Resize settings for image dimensions
     pub resize: ResizeSettings,
-    /// Output format (jpeg, png, webp, avif, or "original")
+    /// Output format (jpeg, png, webp, avif, "original", or "auto")
     #[serde(rename = "outputFormat")]
     pub output_format: String,
+    /// What to do when the optimized file is not smaller than the original
+    #[serde(rename = "sizePolicy", default)]
+    pub size_policy: SizePolicy,
+    /// Minimum savings in percent required by [`SizePolicy::MinSavings`]
+    #[serde(rename = "minSavingsPercent", default)]
+    pub min_savings_percent: Option<f64>,
+    /// Replace the source file instead of writing to `output_path`
+    #[serde(rename = "inPlace", default)]
+    pub in_place: bool,
+    /// Backup handling for in-place writes
+    #[serde(default)]
+    pub backup: BackupSettings,
+    /// What to do when the output path already exists
+    #[serde(rename = "collisionPolicy", default)]
+    pub collision_policy: CollisionPolicy,
+    /// Output path template used when a task has no `output_path`
+    /// (see [`crate::processing::template`] for the supported tokens)
+    #[serde(rename = "outputTemplate", default)]
+    pub output_template: Option<String>,
+    /// Root the `{reldir}` template token is computed against, for mirroring
+    /// a source tree into a separate output root
+    #[serde(rename = "sourceRoot", default)]
+    pub source_root: Option<String>,
+    /// Reuse outputs recorded in the result cache when input and settings are unchanged
+    #[serde(rename = "useCache", default = "default_true")]
+    pub use_cache: bool,
+    /// Skip inputs that were produced by this tool with the same settings or
+    /// are already compressed below the target quality, instead of
+    /// recompressing them; off unless requested
+    #[serde(rename = "skipOptimized", default)]
+    pub skip_optimized: bool,
+    /// Per-format encoder options; unset fields keep the quality-based defaults
+    #[serde(default)]
+    pub advanced: AdvancedSettings,
+    /// Transparency handling for output formats without alpha
+    #[serde(default)]
+    pub alpha: AlphaSettings,
+    /// Candidates and quality threshold for `outputFormat: "auto"`
+    #[serde(default)]
+    pub auto: AutoFormatSettings,
+}
+
+/// How transparency is handled when the output format cannot store it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AlphaSettings {
+    pub policy: AlphaPolicy,
+    /// sRGB colour transparent pixels are flattened onto
+    pub background: [u8; 3],
+}
+
+impl Default for AlphaSettings {
+    fn default() -> Self {
+        Self { policy: AlphaPolicy::Flatten, background: [255, 255, 255] }
+    }
+}
+
+/// What to do with an alpha channel the output format cannot store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AlphaPolicy {
+    /// Composite onto `background`
+    #[default]
+    Flatten,
+    /// Fail the image
+    Keep,
+    /// Write WebP instead, which stores alpha
+    Auto,
+}
+
+/// How `outputFormat: "auto"` picks a format for each image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AutoFormatSettings {
+    /// Formats encoded and compared; on equal size the earlier one wins
+    pub candidates: Vec<String>,
+    /// Largest mean CIEDE2000 difference from the processed source a
+    /// candidate may show; unset accepts every candidate
+    pub max_delta_e: Option<f64>,
+}
+
+impl Default for AutoFormatSettings {
+    fn default() -> Self {
+        Self {
+            candidates: vec!["avif".to_string(), "webp".to_string(), "jpeg".to_string()],
+            max_delta_e: None,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Policy applied when a task's output path is already taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CollisionPolicy {
+    /// Replace the existing file
+    #[default]
+    Overwrite,
+    /// Leave the existing file and skip the task
+    Skip,
+    /// Write to `<stem>-<n>.<ext>` using the first free counter
+    Rename,
+    /// Fail the task, or the whole batch when detected during validation
+    Fail,
+}
+
+/// Backup settings applied before an in-place write replaces its source.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupSettings {
+    /// Backup mode: none, `.bak` sibling, or a backup directory
+    #[serde(default)]
+    pub mode: BackupMode,
+    /// Backup directory for [`BackupMode::Directory`]; relative paths are
+    /// resolved against the source file's directory
+    pub directory: Option<String>,
+}
+
+/// Where the original is preserved before an in-place write.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BackupMode {
+    /// Keep no backup
+    #[default]
+    None,
+    /// Keep the original next to the output as `<name>.bak`
+    Bak,
+    /// Copy the original into `directory`
+    Directory,
+}
+
+/// Policy applied after encoding when the output did not shrink enough.
+///
+/// The default keeps the historical behaviour of always writing the
+/// encoded file, even when it is larger than the source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SizePolicy {
+    /// Always write the encoded output
+    #[default]
+    Always,
+    /// Copy the original file to the output location when the encoded file is larger
+    CopyOriginalIfLarger,
+    /// Write nothing when the encoded file is larger
+    SkipIfLarger,
+    /// Copy the original unless savings reach `minSavingsPercent`
+    MinSavings,
+}
+
+/// Action taken for a task, reported alongside the `success` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OptimizationStatus {
+    /// The encoded output was written
+    Optimized,
+    /// The encoded output was discarded and the original copied in its place
+    CopiedOriginal,
+    /// The encoded output was discarded and nothing was written
+    Skipped,
+    /// The output path already existed and the collision policy skipped the task
+    SkippedExisting,
+    /// An identical earlier run's output was found intact and reused
+    Cached,
+    /// The input is itself an earlier output, or already compressed below the
+    /// target quality, and was left untouched
+    AlreadyOptimized,
+    /// Processing failed; see `error`
+    Failed,
+}
+
+/// Processing priority, trading throughput for a responsive machine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Priority {
+    /// libvips uses as many worker threads as there are cores
+    #[default]
+    Normal,
+    /// libvips is limited to a single worker thread
+    Background,
+}
+
+/// Options for walking a directory tree with `optimize_directory`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DirectoryOptions {
+    /// Glob patterns a file must match; empty accepts every supported image.
+    /// Patterns containing `/` match the path relative to the root, others
+    /// match the file name only
+    pub include: Vec<String>,
+    /// Glob patterns that exclude files and prune whole directories
+    pub exclude: Vec<String>,
+    /// Maximum subdirectory depth; 0 scans only the root, `None` is unlimited
+    pub max_depth: Option<usize>,
+    /// How symbolic links are treated
+    pub symlinks: SymlinkPolicy,
+    /// Whether dot-files and dot-directories are picked up
+    pub include_hidden: bool,
+    /// Root to mirror the source tree into; outputs follow the output
+    /// template when unset
+    pub output_root: Option<String>,
+}
+
+/// Configuration for one watched folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchConfig {
+    /// Directory to monitor
+    pub path: String,
+    /// Settings applied to every image picked up from the folder
+    pub settings: ImageSettings,
+    /// Filters and output location, shared with `optimize_directory`
+    #[serde(default)]
+    pub options: DirectoryOptions,
+    /// Quiet period a file must go without changes before it is processed
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+}
+
+fn default_debounce_ms() -> u64 {
+    1000
+}
+
+/// Symbolic link handling while walking a directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SymlinkPolicy {
+    /// Ignore symlinked files and directories
+    #[default]
+    Skip,
+    /// Follow symlinks; loops are detected and skipped
+    Follow,
 }
 
 /// Quality settings for image compression.
@@ -32,6 +267,161 @@ pub struct QualitySettings {
     pub webp: Option<u32>,
     /// AVIF-specific quality override
     pub avif: Option<u32>,
+    /// Lossy, near-lossless, or lossless encoding per format
+    #[serde(default)]
+    pub mode: CompressionModes,
+}
+
+/// Encoding mode per output format.
+///
+/// An unset format keeps the historical mapping from quality: 100 encodes
+/// losslessly, and AVIF at 90 or above uses near-lossless 4:4:4 chroma.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionModes {
+    /// JPEG has no lossless mode; only `lossy` is accepted
+    pub jpeg: Option<Compression>,
+    /// PNG is either palette-quantised (`lossy`) or `lossless`
+    pub png: Option<Compression>,
+    pub webp: Option<Compression>,
+    pub avif: Option<Compression>,
+}
+
+/// How much an encoder may discard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Compression {
+    /// Discard detail according to the quality setting
+    Lossy,
+    /// Lossless-like fidelity at a fraction of the size; quality sets how close
+    NearLossless,
+    /// Keep every pixel; quality is ignored
+    Lossless,
+}
+
+/// Expert encoder options, one block per output format.
+///
+/// Every field is optional. An unset field keeps the default the encoder
+/// picks from the effective quality (see `processing/libvips/formats.rs`);
+/// only the block of the format actually written is used.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdvancedSettings {
+    pub jpeg: JpegAdvanced,
+    pub png: PngAdvanced,
+    pub webp: WebpAdvanced,
+    pub avif: AvifAdvanced,
+}
+
+/// Expert JPEG encoder options.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct JpegAdvanced {
+    /// Write progressive scans with optimized scan order
+    pub progressive: Option<bool>,
+    /// Trellis quantisation
+    pub trellis: Option<bool>,
+    /// Overshoot deringing, for images with sharp black-on-white edges
+    pub deringing: Option<bool>,
+    /// Quantization table (0-8); 3 is ImageMagick's, tuned by mozjpeg
+    pub quant_table: Option<u32>,
+    /// Chroma subsampling
+    pub subsampling: Option<ChromaSubsampling>,
+}
+
+/// Expert PNG encoder options.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PngAdvanced {
+    /// Quantise to an indexed palette
+    pub palette: Option<bool>,
+    /// Palette size (2-256), rounded up to 2, 4, 16, or 256; implies a palette
+    pub colors: Option<u32>,
+    /// Dithering strength while quantising (0.0-1.0)
+    pub dither: Option<f64>,
+    /// Adam7 interlacing
+    pub interlace: Option<bool>,
+    /// Row filter
+    pub filter: Option<PngFilter>,
+}
+
+/// Expert WebP encoder options.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebpAdvanced {
+    /// Content preset tuning the encoder
+    pub preset: Option<WebpPreset>,
+    /// CPU effort (0-6)
+    pub effort: Option<u32>,
+    /// Sharper RGB→YUV conversion for lossy output
+    pub smart_subsample: Option<bool>,
+    /// Deprecated: use `quality.mode.webp = "nearLossless"`. `true` selects
+    /// near-lossless when no WebP mode is set.
+    pub near_lossless: Option<bool>,
+    /// Quality of the alpha channel (0-100); defaults to the image quality
+    pub alpha_quality: Option<u32>,
+}
+
+/// Expert AVIF encoder options.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AvifAdvanced {
+    /// CPU effort (0-9)
+    pub effort: Option<u32>,
+    /// Bits per sample: 8, 10, or 12
+    pub bit_depth: Option<u32>,
+    /// Chroma subsampling
+    pub subsampling: Option<ChromaSubsampling>,
+    /// AV1 encoder library
+    pub encoder: Option<AvifEncoder>,
+}
+
+/// Chroma subsampling mode for JPEG and AVIF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChromaSubsampling {
+    /// 4:4:4 at high quality, 4:2:0 below
+    Auto,
+    /// Always 4:2:0
+    On,
+    /// Always 4:4:4
+    Off,
+}
+
+/// PNG row filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PngFilter {
+    None,
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    /// Try every filter per row (adaptive)
+    All,
+}
+
+/// libwebp content preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WebpPreset {
+    Default,
+    Picture,
+    Photo,
+    Drawing,
+    Icon,
+    Text,
+}
+
+/// AV1 encoder used for AVIF output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AvifEncoder {
+    /// Whichever encoder libheif prefers
+    Auto,
+    Aom,
+    Rav1e,
+    Svt,
 }
 
 /// Resize settings for image dimensions.
@@ -56,7 +446,8 @@ pub struct ResizeSettings {
 ///
 /// Contains the original and optimized file information along with
 /// compression statistics.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StoredResult")]
 pub struct OptimizationResult {
     /// Path to the original input file
     pub original_path: String,
@@ -68,12 +459,158 @@ pub struct OptimizationResult {
     pub optimized_size: u64,
     /// Whether the optimization succeeded
     pub success: bool,
+    /// Which action was taken for this task
+    pub status: OptimizationStatus,
     /// Error message if optimization failed
     pub error: Option<String>,
+    /// Stable code of the failure, for localization and programmatic handling
+    #[serde(rename = "errorCode", default)]
+    pub error_code: Option<ErrorCode>,
+    /// Raw library or OS message behind the failure
+    #[serde(rename = "errorDetail", default)]
+    pub error_detail: Option<String>,
     /// Bytes saved (can be negative if file grew)
     #[serde(rename = "savedBytes")]
     pub saved_bytes: i64,
     /// Compression ratio as a percentage
     #[serde(rename = "compressionRatio")]
     pub compression_ratio: f64,
-} 
\ No newline at end of file
+    /// Time spent in each processing phase
+    #[serde(default)]
+    pub timings: PhaseTimings,
+    /// What was decoded, transformed, and encoded
+    #[serde(flatten, default)]
+    pub details: ImageDetails,
+}
+
+/// An [`OptimizationResult`] as persisted, possibly by a version that
+/// predates `status`.
+#[derive(Deserialize)]
+struct StoredResult {
+    original_path: String,
+    optimized_path: String,
+    original_size: u64,
+    optimized_size: u64,
+    success: bool,
+    #[serde(default)]
+    status: Option<OptimizationStatus>,
+    error: Option<String>,
+    #[serde(rename = "errorCode", default)]
+    error_code: Option<ErrorCode>,
+    #[serde(rename = "errorDetail", default)]
+    error_detail: Option<String>,
+    #[serde(rename = "savedBytes")]
+    saved_bytes: i64,
+    #[serde(rename = "compressionRatio")]
+    compression_ratio: f64,
+    #[serde(default)]
+    timings: PhaseTimings,
+    #[serde(flatten, default)]
+    details: ImageDetails,
+}
+
+impl From<StoredResult> for OptimizationResult {
+    fn from(stored: StoredResult) -> Self {
+        // Older results only recorded whether the task succeeded
+        let status = stored.status.unwrap_or(if stored.success {
+            OptimizationStatus::Optimized
+        } else {
+            OptimizationStatus::Failed
+        });
+        Self {
+            original_path: stored.original_path,
+            optimized_path: stored.optimized_path,
+            original_size: stored.original_size,
+            optimized_size: stored.optimized_size,
+            success: stored.success,
+            status,
+            error: stored.error,
+            error_code: stored.error_code,
+            error_detail: stored.error_detail,
+            saved_bytes: stored.saved_bytes,
+            compression_ratio: stored.compression_ratio,
+            timings: stored.timings,
+            details: stored.details,
+        }
+    }
+}
+
+/// Wall-clock time spent in each phase of one task, in milliseconds.
+///
+/// libvips evaluates lazily: opening an image only reads its header and a
+/// resize only builds the pipeline, so most pixel decoding and resampling is
+/// attributed to `encodeMs`, where the pipeline actually runs. Shrink-on-load
+/// resizes (`vips_thumbnail`) combine decode and resize and count as resize.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseTimings {
+    /// Opening or reading the source
+    pub decode_ms: f64,
+    /// Building the resize pipeline
+    pub resize_ms: f64,
+    /// Encoding and writing the output
+    pub encode_ms: f64,
+    /// Applying the size policy and moving the output into place
+    #[serde(default)]
+    pub write_ms: f64,
+}
+
+impl PhaseTimings {
+    /// Sum of all phases.
+    pub fn total_ms(&self) -> f64 {
+        self.decode_ms + self.resize_ms + self.encode_ms + self.write_ms
+    }
+}
+
+/// Facts about one task's input and output, serialized flat into
+/// [`OptimizationResult`] so consumers need not re-probe the files.
+///
+/// Fields are `None` when the task never decoded the image (skips, failures)
+/// or when they do not apply (dimensions and quality of SVGs).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageDetails {
+    pub source_width: Option<u32>,
+    pub source_height: Option<u32>,
+    pub output_width: Option<u32>,
+    pub output_height: Option<u32>,
+    /// Format of the input detected from its content, e.g. `jpeg`
+    pub input_format: Option<String>,
+    /// The input's extension names a different format than its content
+    #[serde(default)]
+    pub extension_mismatch: bool,
+    /// Format actually written; the input format when the original was kept
+    pub output_format: Option<String>,
+    /// Bits per sample of the decoded source, e.g. 16 for a 16-bit PNG
+    pub source_bit_depth: Option<u32>,
+    /// Bits per sample written (per palette index for palette PNG); below the
+    /// source depth means it was dithered
+    pub output_bit_depth: Option<u32>,
+    /// Encoder quality used for `outputFormat`
+    pub quality: Option<u32>,
+    /// The image was scaled
+    #[serde(default)]
+    pub resized: bool,
+    /// Part of the image was cut away; no resize mode crops yet
+    #[serde(default)]
+    pub cropped: bool,
+    /// The image was turned upright according to its EXIF orientation
+    #[serde(default)]
+    pub rotated: bool,
+    /// Formats tried by `outputFormat: "auto"`, smallest first; empty otherwise
+    #[serde(default)]
+    pub format_candidates: Vec<FormatCandidate>,
+}
+
+/// One format encoded by `outputFormat: "auto"`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatCandidate {
+    pub format: String,
+    /// Encoded size in bytes
+    pub size: u64,
+    /// Mean CIEDE2000 difference from the processed source; `None` without a threshold
+    pub delta_e: Option<f64>,
+    /// Over the `maxDeltaE` threshold; chosen only when every candidate is
+    pub rejected: bool,
+}
@@ -13,7 +13,12 @@ use tracing::{debug, info};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 use tauri::Manager;
 use crate::core::AppState;
-use crate::commands::{optimize_image, optimize_images};
+use crate::commands::{
+    discard_job, export_report, get_job_results, get_job_status, get_limits, get_priority,
+    list_jobs, list_resumable_jobs, list_watches, optimize_directory, optimize_image,
+    optimize_images, pause_job, probe_images, resume_job, set_limits, set_priority, start_watch,
+    stop_watch,
+};
 
 // Import the window-vibrancy crate only on macOS
 #[cfg(target_os = "macos")]
@@ -61,6 +66,23 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             optimize_image,
             optimize_images,
+            optimize_directory,
+            get_job_status,
+            list_jobs,
+            get_job_results,
+            list_resumable_jobs,
+            pause_job,
+            resume_job,
+            discard_job,
+            set_priority,
+            get_priority,
+            set_limits,
+            get_limits,
+            export_report,
+            probe_images,
+            start_watch,
+            stop_watch,
+            list_watches,
         ])
         .setup(|app| {
             // Initialize AppState with app handle
@@ -0,0 +1,103 @@
+// src-tauri/src/processing/libvips/alpha.rs
+
+//! Transparency handling for outputs that cannot store alpha.
+//!
+//! Left to itself, the JPEG saver drops the alpha band and semi-transparent
+//! pixels come out black or fringed. [`AlphaPolicy`] decides instead: flatten
+//! onto a background colour, refuse, or switch to a format with alpha.
+//!
+//! Resizing needs no special care: `vips_thumbnail` premultiplies alpha
+//! before shrinking and unpremultiplies afterwards, so transparent pixels
+//! never bleed their colour into visible neighbours. Flattening runs after
+//! the resize, on the unpremultiplied result.
+
+use libvips::VipsImage;
+use libvips::ops::{self, BandFormat, FlattenOptions};
+
+use crate::core::{AlphaPolicy, AlphaSettings, ImageSettings};
+use crate::utils::error::ErrorCode;
+use crate::utils::{OptimizerError, OptimizerResult, resolve_output_format};
+
+use super::auto::{eligible_formats, is_auto};
+use super::ErrorMark;
+
+/// Format chosen by [`AlphaPolicy::Auto`] when the requested one has no alpha.
+const AUTO_ALPHA_FORMAT: &str = "webp";
+
+/// Returns `true` when `format` can store an alpha channel.
+pub fn supports_alpha(format: &str) -> bool {
+    format != "jpeg"
+}
+
+/// Resolves the output format of the image at `input_path`, switching to a
+/// format with alpha when the policy is [`AlphaPolicy::Auto`] and the
+/// source header has an alpha channel the requested format cannot store.
+///
+/// With `outputFormat: "auto"` this is the first eligible candidate; the
+/// executor re-targets the extension once encoding has picked the format.
+pub fn target_format(input_path: &str, settings: &ImageSettings) -> OptimizerResult<String> {
+    if is_auto(settings) {
+        return Ok(eligible_formats(input_path, settings)?.formats.swap_remove(0));
+    }
+    let format = resolve_output_format(input_path, &settings.output_format)?;
+    if settings.alpha.policy != AlphaPolicy::Auto || supports_alpha(&format) {
+        return Ok(format);
+    }
+    let mark = ErrorMark::now();
+    let header = VipsImage::new_from_file(input_path).map_err(|_| {
+        mark.error(ErrorCode::DecodeFailed, format!("Failed to probe '{input_path}'"))
+            .with_path(input_path)
+            .with_operation("probe")
+    })?;
+    if header.image_hasalpha() {
+        return Ok(AUTO_ALPHA_FORMAT.to_string());
+    }
+    Ok(format)
+}
+
+/// Applies `alpha` to `image` before it is saved as `format`.
+///
+/// Images without alpha, and formats that store it, pass through unchanged.
+pub fn apply_alpha_policy(
+    image: VipsImage,
+    format: &str,
+    alpha: &AlphaSettings,
+) -> OptimizerResult<VipsImage> {
+    if !image.image_hasalpha() || supports_alpha(format) {
+        return Ok(image);
+    }
+    match alpha.policy {
+        AlphaPolicy::Keep => Err(OptimizerError::new(
+            ErrorCode::AlphaUnsupported,
+            format!("{} cannot store transparency", format.to_uppercase()),
+        )
+        .with_format(format)),
+        // Auto only reaches here when the format was fixed elsewhere
+        AlphaPolicy::Flatten | AlphaPolicy::Auto => flatten(&image, alpha.background),
+    }
+}
+
+/// Composites `image` over an opaque `background` (8-bit sRGB).
+fn flatten(image: &VipsImage, background: [u8; 3]) -> OptimizerResult<VipsImage> {
+    // The background and alpha are in the image's own sample range
+    let max_alpha = match image.get_format() {
+        Ok(BandFormat::Ushort) => 65535.0,
+        Ok(BandFormat::Float | BandFormat::Double) => 1.0,
+        _ => 255.0,
+    };
+    let scale = max_alpha / 255.0;
+    let [r, g, b] = background.map(f64::from);
+    let background = if image.get_bands() <= 2 {
+        // Grey + alpha: the background's luma
+        vec![(0.299 * r + 0.587 * g + 0.114 * b) * scale]
+    } else {
+        vec![r * scale, g * scale, b * scale]
+    };
+
+    let opts = FlattenOptions { background, max_alpha };
+    let mark = ErrorMark::now();
+    ops::flatten_with_opts(image, &opts).map_err(|_| {
+        mark.error(ErrorCode::ProcessingFailed, "Flattening transparency failed")
+            .with_operation("flatten")
+    })
+}
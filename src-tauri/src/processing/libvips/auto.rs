@@ -0,0 +1,224 @@
+// src-tauri/src/processing/libvips/auto.rs
+
+//! Per-image format choice for `outputFormat: "auto"`.
+//!
+//! Every eligible candidate is encoded into memory from the same processed
+//! image and the smallest encoding is written. Candidates must be able to
+//! store what the source has: formats without alpha are passed over for
+//! transparent sources unless the alpha policy flattens, and animated
+//! sources only try formats that keep every frame.
+//!
+//! With `maxDeltaE` set, each encoding is decoded again and its mean
+//! CIEDE2000 difference from the processed image measured. Candidates above
+//! the threshold lose to any that stay within it; when none do, the closest
+//! one is written rather than failing the image.
+
+use libvips::VipsImage;
+use libvips::ops::{self, ExtractBandOptions};
+use tracing::{debug, warn};
+
+use crate::core::{AlphaPolicy, FormatCandidate, ImageSettings};
+use crate::utils::error::ErrorCode;
+use crate::utils::{OptimizerError, OptimizerResult, normalize_format};
+
+use super::alpha::{apply_alpha_policy, supports_alpha};
+use super::formats::encode_image_as;
+use super::ErrorMark;
+
+/// `outputFormat` value that picks the format per image.
+pub const AUTO_FORMAT: &str = "auto";
+
+/// Returns `true` when `settings` leave the format to [`choose_format`].
+pub fn is_auto(settings: &ImageSettings) -> bool {
+    settings.output_format.eq_ignore_ascii_case(AUTO_FORMAT)
+}
+
+/// Returns `true` when `format` stores every frame of an animation.
+pub fn supports_animation(format: &str) -> bool {
+    format == "webp"
+}
+
+/// Candidates one source may be written as, from its header.
+pub struct Eligible {
+    /// Normalized formats in configured order; never empty
+    pub formats: Vec<String>,
+    /// The source has several frames, all of which must be loaded
+    pub animated: bool,
+}
+
+/// Filters the configured candidates down to those that can store the
+/// image at `input_path`.
+pub fn eligible_formats(input_path: &str, settings: &ImageSettings) -> OptimizerResult<Eligible> {
+    let mark = ErrorMark::now();
+    let header = VipsImage::new_from_file(input_path).map_err(|_| {
+        mark.error(ErrorCode::DecodeFailed, format!("Failed to probe '{input_path}'"))
+            .with_path(input_path)
+            .with_operation("probe")
+    })?;
+    let animated = header.get_n_pages() > 1;
+    let transparent = header.image_hasalpha() && settings.alpha.policy != AlphaPolicy::Flatten;
+
+    let mut formats: Vec<String> = Vec::new();
+    for format in settings.auto.candidates.iter().map(|f| normalize_format(f)) {
+        if !formats.contains(&format) {
+            formats.push(format);
+        }
+    }
+    if animated {
+        formats.retain(|f| supports_animation(f));
+        if formats.is_empty() {
+            return Err(OptimizerError::new(
+                ErrorCode::UnsupportedFormat,
+                "No auto candidate can store an animated image",
+            )
+            .with_path(input_path));
+        }
+    }
+    if transparent {
+        formats.retain(|f| supports_alpha(f));
+        if formats.is_empty() {
+            return Err(OptimizerError::new(
+                ErrorCode::AlphaUnsupported,
+                "No auto candidate can store transparency",
+            )
+            .with_path(input_path));
+        }
+    }
+    Ok(Eligible { formats, animated })
+}
+
+/// The encoding [`choose_format`] settled on.
+pub struct AutoChoice {
+    pub format: String,
+    pub bytes: Vec<u8>,
+    /// Bits per sample written
+    pub bit_depth: u32,
+    /// Every candidate that encoded, smallest first
+    pub candidates: Vec<FormatCandidate>,
+}
+
+/// One candidate held in memory until the choice is made.
+struct Encoding {
+    format: String,
+    bytes: Vec<u8>,
+    bit_depth: u32,
+    delta_e: Option<f64>,
+}
+
+/// Encodes `image` as each of `formats` and keeps the smallest encoding
+/// within the `maxDeltaE` threshold of `settings`.
+///
+/// `image` is read once per candidate, so it must not be a sequential
+/// pipeline. A candidate that fails to encode is skipped; the image only
+/// fails when every candidate does.
+pub fn choose_format(
+    image: &VipsImage,
+    formats: &[String],
+    settings: &ImageSettings,
+) -> OptimizerResult<AutoChoice> {
+    let max_delta_e = settings.auto.max_delta_e;
+
+    let mut encodings = Vec::with_capacity(formats.len());
+    let mut last_error = None;
+    for format in formats {
+        match encode_candidate(image, format, settings) {
+            Ok(encoding) => {
+                debug!(
+                    "Auto candidate {}: {} bytes, ΔE {:?}",
+                    format,
+                    encoding.bytes.len(),
+                    encoding.delta_e
+                );
+                encodings.push(encoding);
+            }
+            Err(e) => {
+                warn!("Auto candidate {} failed: {}", format, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    if encodings.is_empty() {
+        return Err(last_error.unwrap_or_else(|| {
+            OptimizerError::new(ErrorCode::InvalidSettings, "No auto candidates configured")
+        }));
+    }
+
+    // Stable, so equal sizes keep the configured order
+    encodings.sort_by_key(|e| e.bytes.len());
+    let within = |e: &Encoding| match (e.delta_e, max_delta_e) {
+        (Some(delta_e), Some(max)) => delta_e <= max,
+        _ => true,
+    };
+    let chosen = encodings.iter().position(within).unwrap_or_else(|| {
+        encodings
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                a.delta_e.unwrap_or(0.0).total_cmp(&b.delta_e.unwrap_or(0.0))
+            })
+            .map_or(0, |(i, _)| i)
+    });
+
+    let candidates = encodings
+        .iter()
+        .map(|e| FormatCandidate {
+            format: e.format.clone(),
+            size: e.bytes.len() as u64,
+            delta_e: e.delta_e,
+            rejected: !within(e),
+        })
+        .collect();
+    let Encoding { format, bytes, bit_depth, .. } = encodings.swap_remove(chosen);
+    Ok(AutoChoice { format, bytes, bit_depth, candidates })
+}
+
+/// Encodes `image` as `format` after the alpha policy, measuring the
+/// difference when a threshold is set.
+fn encode_candidate(
+    image: &VipsImage,
+    format: &str,
+    settings: &ImageSettings,
+) -> OptimizerResult<Encoding> {
+    let mark = ErrorMark::now();
+    let source = ops::copy(image).map_err(|_| {
+        mark.error(ErrorCode::ProcessingFailed, "Copying the image failed").with_operation("copy")
+    })?;
+    let prepared = apply_alpha_policy(source, format, &settings.alpha)?;
+    let (bytes, bit_depth) =
+        encode_image_as(&prepared, format, &settings.quality, &settings.advanced)?;
+    let delta_e = match settings.auto.max_delta_e {
+        Some(_) => Some(mean_delta_e(&prepared, &bytes).map_err(|e| e.with_format(format))?),
+        None => None,
+    };
+    Ok(Encoding { format: format.to_string(), bytes, bit_depth, delta_e })
+}
+
+/// Mean CIEDE2000 difference between `reference` and `encoded` once decoded.
+///
+/// Alpha is left out; flattening is the policy's doing, not the encoder's.
+/// Animated references are compared frame by frame, so every page of the
+/// candidate is decoded too.
+fn mean_delta_e(reference: &VipsImage, encoded: &[u8]) -> OptimizerResult<f64> {
+    let options = if reference.get_n_pages() > 1 { "n=-1" } else { "" };
+    let mark = ErrorMark::now();
+    let decoded = VipsImage::new_from_buffer(encoded, options).map_err(|_| {
+        mark.error(ErrorCode::DecodeFailed, "Failed to decode auto candidate")
+            .with_operation("compare")
+    })?;
+    let (reference, decoded) = (colour_bands(reference)?, colour_bands(&decoded)?);
+    let mark = ErrorMark::now();
+    let difference = ops::d_e00(&reference, &decoded).and_then(|diff| ops::avg(&diff));
+    difference.map_err(|_| {
+        mark.error(ErrorCode::ProcessingFailed, "Comparing auto candidate failed")
+            .with_operation("compare")
+    })
+}
+
+/// `image` without its alpha band.
+fn colour_bands(image: &VipsImage) -> OptimizerResult<VipsImage> {
+    let n = image.get_bands() - i32::from(image.image_hasalpha());
+    let mark = ErrorMark::now();
+    ops::extract_band_with_opts(image, 0, &ExtractBandOptions { n }).map_err(|_| {
+        mark.error(ErrorCode::ProcessingFailed, "Dropping alpha failed").with_operation("compare")
+    })
+}
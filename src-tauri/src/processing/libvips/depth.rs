@@ -0,0 +1,76 @@
+// src-tauri/src/processing/libvips/depth.rs
+
+//! Bit-depth handling for high-bit-depth sources.
+//!
+//! 16-bit PNG and TIFF masters decode to `ushort` samples and float HDR
+//! sources to `float`. Formats that can hold more than 8 bits (16-bit PNG,
+//! 10/12-bit AVIF) receive them as they are; for 8-bit targets the image is
+//! reduced here with noise dithering, because the plain bit shift libvips
+//! applies on save turns smooth gradients into visible bands.
+
+use libvips::VipsImage;
+use libvips::ops::{self, BandFormat, GaussnoiseOptions, Interpretation};
+
+use crate::utils::OptimizerError;
+use crate::utils::error::ErrorCode;
+
+use super::probe::sample_size;
+use super::ErrorMark;
+
+type Result<T> = std::result::Result<T, OptimizerError>;
+
+/// Standard deviation of the dither noise, in 8-bit steps.
+const DITHER_SIGMA: f64 = 0.5;
+
+/// One 8-bit step in 16-bit units.
+const STEP_16: f64 = 257.0;
+
+/// Bits per sample of `image`, e.g. 8 for `uchar` and 16 for `ushort`.
+pub fn bit_depth(image: &VipsImage) -> u32 {
+    image.get_format().map_or(8, |format| sample_size(format) as u32 * 8)
+}
+
+/// Reduces a high-bit-depth image to 8 bits per sample.
+///
+/// The image is brought to 16-bit RGB or grey first (float HDR is
+/// tone-clipped by libvips on the way), then gaussian noise of about half
+/// an 8-bit step is added to the colour bands before they are rounded down.
+/// Alpha is rounded without noise so opaque pixels stay fully opaque.
+pub fn dither_to_8bit(image: &VipsImage) -> Result<VipsImage> {
+    let mark = ErrorMark::now();
+    dither(image).map_err(|_| {
+        mark.error(ErrorCode::ProcessingFailed, "Dithering to 8 bits failed")
+            .with_operation("dither")
+    })
+}
+
+fn dither(image: &VipsImage) -> libvips::Result<VipsImage> {
+    let grey = matches!(
+        image.get_interpretation(),
+        Ok(Interpretation::BW | Interpretation::Grey16)
+    );
+    let (wide, narrow) = if grey {
+        (Interpretation::Grey16, Interpretation::BW)
+    } else {
+        (Interpretation::Rgb16, Interpretation::Srgb)
+    };
+    let image16 = ops::colourspace(image, wide)?;
+
+    let bands = image16.get_bands().max(1) as usize;
+    let colour_bands = if image16.image_hasalpha() { bands - 1 } else { bands };
+    // A 1-band noise image becomes one band per constant; alpha gets none.
+    let mut scale: Vec<f64> =
+        (0..bands).map(|b| if b < colour_bands { DITHER_SIGMA * STEP_16 } else { 0.0 }).collect();
+    // Half a step, so the shift down to 8 bits rounds instead of truncating
+    let mut offset = vec![STEP_16 / 2.0; bands];
+    let noise = ops::gaussnoise_with_opts(
+        image16.get_width(),
+        image16.get_height(),
+        &GaussnoiseOptions { sigma: 1.0, mean: 0.0, ..GaussnoiseOptions::default() },
+    )?;
+    let noise = ops::linear(&noise, &mut scale, &mut offset)?;
+
+    // `add` keeps the header of its left operand, so the sum is still RGB16/GREY16
+    let noisy = ops::cast(&ops::add(&image16, &noise)?, BandFormat::Ushort)?;
+    ops::colourspace(&noisy, narrow)
+}
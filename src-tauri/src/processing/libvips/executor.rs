@@ -7,6 +7,7 @@
 //! `tokio::task::spawn_blocking` call so the async runtime is never blocked.
 
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use tauri::AppHandle;
 use tauri::Emitter;
@@ -15,152 +16,394 @@ use tracing::{debug, warn};
 use libvips::VipsImage;
 use libvips::ops::Access;
 
-use crate::core::{ImageTask, OptimizationResult};
-use crate::utils::{ImageFormat, OptimizerError, OptimizerResult, extract_filename, format_from_extension, normalize_format};
-
-use super::formats::save_image_as;
+use crate::core::{
+    CollisionPolicy, ImageDetails, ImageSettings, ImageTask, JobQueue, MemoryBudget, MemoryPlan,
+    OptimizationResult, OptimizationStatus, PauseGate, PhaseTimings, ResourceLimits, ResultCache,
+};
+use crate::processing::output::{StagedOutput, finalize_output, resolve_collision, same_path};
+use crate::processing::progress::{
+    IMAGE_PROGRESS_EVENT, ImageProgress, ItemProgress, JOB_FINISHED_EVENT, JobFinishedEvent,
+    JobSummary, PROGRESS_EVENT, ProgressStatus, RunClock,
+};
+use crate::utils::error::ErrorCode;
+use crate::utils::{
+    DetectedFormat, FormatSource, ImageFormat, OptimizerError, OptimizerResult, detect_format,
+    ensure_correct_extension, estimate_jpeg_quality, extract_filename, file_sha256,
+    format_from_loader, normalize_format, resolve_output_format,
+};
+
+use super::alpha::{apply_alpha_policy, target_format};
+use super::auto::{Eligible, choose_format, eligible_formats, is_auto, supports_animation};
+use super::depth::bit_depth;
+use super::formats::{effective_quality, save_image_as};
+use super::probe::probe_header;
+use super::ErrorMark;
 use super::resize::{apply_resize, needs_resize, load_and_resize};
 
+/// Tasks probed per blocking call during pre-flight.
+const PREFLIGHT_CHUNK: usize = 64;
+
+/// Receives the percent complete of the image being encoded.
+type EvalCallback = Box<dyn FnMut(i32) + Send>;
+
 /// Executor that processes images directly via libvips with no subprocess overhead.
 pub struct NativeExecutor {
     app: AppHandle,
+    cache: Arc<ResultCache>,
+    limits: Arc<Mutex<ResourceLimits>>,
+    budget: Arc<MemoryBudget>,
 }
 
 impl NativeExecutor {
-    pub fn new(app: AppHandle) -> Self {
-        Self { app }
+    pub fn new(
+        app: AppHandle,
+        cache: Arc<ResultCache>,
+        limits: Arc<Mutex<ResourceLimits>>,
+        budget: Arc<MemoryBudget>,
+    ) -> Self {
+        Self { app, cache, limits, budget }
+    }
+
+    /// Snapshot of the limits, taken per image so changes apply to the next one.
+    fn limits(&self) -> ResourceLimits {
+        *self.limits.lock().unwrap_or_else(|e| e.into_inner())
     }
 
-    /// Processes a chunk of tasks, emitting progress events with **overall** job counts.
+    /// Consumes up to `max_tasks` pending tasks from `queue`, emitting a
+    /// [`ProgressEvent`](crate::processing::progress::ProgressEvent) with
+    /// **overall** job counts after each one. Throughput and ETA come from `clock`.
     ///
-    /// `offset` is the number of tasks already completed in prior chunks so that
-    /// `completedTasks` and `totalTasks` in emitted events reflect the full job,
-    /// not just this chunk.
+    /// Before an image is decoded its estimate (from `plan`) is reserved from
+    /// the process-wide [`MemoryBudget`]; dispatch waits while that would push
+    /// images running in other jobs, or libvips' tracked memory, over the
+    /// ceiling. Within one call images run one at a time.
+    ///
+    /// `pause` is checked before each image; while it is closed a `paused`
+    /// progress event is emitted and the batch waits without holding any image.
+    ///
+    /// Each outcome is recorded in the queue as soon as it is known, so a
+    /// persisted queue can resume after a crash. `completedTasks` and
+    /// `totalTasks` in emitted events cover the whole queue, including tasks
+    /// finished in earlier calls or before a restart.
     ///
     /// libvips uses its own internal thread pool for within-image parallelism, so
     /// sequential dispatch here is intentional and avoids thread oversubscription.
+    ///
+    /// Tasks whose input, settings, and output are unchanged since an earlier
+    /// run are answered from the result cache, which is saved before returning.
     pub async fn execute_batch(
         &self,
-        tasks: &[ImageTask],
-        offset: usize,
-        job_total: usize,
-        job_start: Instant,
+        queue: &JobQueue,
+        pause: &PauseGate,
+        plan: &MemoryPlan,
+        max_tasks: usize,
+        clock: &RunClock,
     ) -> OptimizerResult<Vec<OptimizationResult>> {
-        let mut results = Vec::with_capacity(tasks.len());
+        let mut results = Vec::new();
+
+        while results.len() < max_tasks
+            && let Some((index, task)) = queue.next_pending()
+        {
+            self.wait_if_paused(queue, pause, clock).await;
 
-        for (idx, task) in tasks.iter().enumerate() {
-            let overall_completed = offset + idx + 1;
             let task_clone = task.clone();
+            let cache = self.cache.clone();
+            let limits = self.limits();
+            let reservation = self.budget.reserve(plan.estimate(index)).await;
+            let on_eval = self.eval_callback(queue, &task);
+
+            let result = tokio::task::spawn_blocking(move || {
+                optimize_cached(&task_clone, &cache, &limits, on_eval)
+            })
+            .await
+            .map_err(|e| OptimizerError::processing(format!("Task panicked: {e}")))?;
+            drop(reservation);
+
+            let opt_result = match result {
+                Ok(opt_result) => opt_result,
+                Err(e) => {
+                    warn!("Optimization failed for {}: {}", task.input_path, e);
+                    failed_result(&task, &e)
+                }
+            };
 
-            let result = tokio::task::spawn_blocking(move || optimize_single(&task_clone))
-                .await
-                .map_err(|e| OptimizerError::processing(format!("Task panicked: {e}")))?;
+            self.complete(queue, clock, index, &task, &opt_result);
+            results.push(opt_result);
+        }
 
-            match result {
-                Ok(opt_result) => {
-                    self.emit_progress(overall_completed, job_total, job_start, task, &opt_result);
-                    results.push(opt_result);
-                }
-                Err(e) => {
-                    let error_msg = e.to_string();
-                    warn!("Optimization failed for {}: {}", task.input_path, error_msg);
-
-                    self.emit_error_progress(overall_completed, job_total, task, &error_msg);
-
-                    results.push(OptimizationResult {
-                        original_path: task.input_path.clone(),
-                        optimized_path: task.output_path.clone(),
-                        original_size: std::fs::metadata(&task.input_path)
-                            .map(|m| m.len())
-                            .unwrap_or(0),
-                        optimized_size: 0,
-                        success: false,
-                        error: Some(error_msg),
-                        saved_bytes: 0,
-                        compression_ratio: 0.0,
-                    });
+        if let Err(e) = self.cache.flush() {
+            warn!("Failed to save optimization cache: {e}");
+        }
+        debug!(
+            "Job {}: libvips memory {} MiB (peak {} MiB, ceiling {} MiB)",
+            queue.id(),
+            self.budget.in_use() >> 20,
+            self.budget.peak() >> 20,
+            self.budget.ceiling() >> 20
+        );
+
+        Ok(results)
+    }
+
+    /// Pre-flight stage: probes the header of every pending task and fails
+    /// the ones that are truncated, corrupt, or too large before any encoding
+    /// starts. Each flagged task gets a `failed` result and a progress event.
+    ///
+    /// Tasks are probed [`PREFLIGHT_CHUNK`] at a time; a `checking` progress
+    /// event follows each chunk, and `pause` is honoured between chunks.
+    /// SVGs are not parsed here, since their pipeline parses them anyway.
+    ///
+    /// Returns the estimated decoded memory of every task that passed, for
+    /// [`execute_batch`](Self::execute_batch) to reserve from the budget.
+    pub async fn preflight(
+        &self,
+        queue: &JobQueue,
+        pause: &PauseGate,
+        clock: &RunClock,
+    ) -> OptimizerResult<MemoryPlan> {
+        let pending = queue.pending();
+        let job_total = queue.total();
+        let mut flagged = 0;
+        let mut plan = MemoryPlan::default();
+
+        for chunk in pending.chunks(PREFLIGHT_CHUNK) {
+            self.wait_if_paused(queue, pause, clock).await;
+
+            let inputs: Vec<String> =
+                chunk.iter().map(|(_, task)| task.input_path.clone()).collect();
+            let limits = self.limits();
+            let probes = tokio::task::spawn_blocking(move || {
+                inputs.iter().map(|path| probe_header(path, &limits)).collect::<Vec<_>>()
+            })
+            .await
+            .map_err(|e| OptimizerError::processing(format!("Pre-flight probe panicked: {e}")))?;
+
+            for ((index, task), probe) in chunk.iter().zip(probes) {
+                if let Some(e) = probe.error {
+                    warn!("Pre-flight rejected {}: {}", task.input_path, e);
+                    self.complete(queue, clock, *index, task, &failed_result(task, &e));
+                    flagged += 1;
+                } else if let Some(bytes) = probe.estimated_memory {
+                    let pages = probe.pages.unwrap_or(1) as u64;
+                    let loaded = if loads_every_page(&task.settings, pages) { pages } else { 1 };
+                    plan.insert(*index, bytes.saturating_mul(loaded));
                 }
             }
+
+            let completed = queue.finished();
+            if completed < job_total {
+                let event =
+                    clock.event(queue.id(), completed, job_total, ProgressStatus::Checking, None);
+                self.emit(PROGRESS_EVENT, event);
+            }
+        }
+        if flagged > 0 {
+            debug!("Job {}: pre-flight flagged {} of {} images", queue.id(), flagged, job_total);
         }
+        debug!("Job {}: ~{} MiB to decode", queue.id(), plan.total() >> 20);
+        Ok(plan)
+    }
 
-        Ok(results)
+    /// Holds the job while `pause` is closed, emitting a `paused` progress event.
+    async fn wait_if_paused(&self, queue: &JobQueue, pause: &PauseGate, clock: &RunClock) {
+        if !pause.is_paused() {
+            return;
+        }
+        debug!("Job {} paused", queue.id());
+        let (completed, total) = (queue.finished(), queue.total());
+        let event = clock.event(queue.id(), completed, total, ProgressStatus::Paused, None);
+        self.emit(PROGRESS_EVENT, event);
+
+        let paused_at = Instant::now();
+        pause.wait_resumed().await;
+        clock.add_paused(paused_at.elapsed());
+        debug!("Job {} resumed", queue.id());
     }
 
-    // ── Progress emission ────────────────────────────────────────────────────────────
+    /// Emits rate-limited progress events while `task` is encoded, so a huge
+    /// image shows movement before it finishes.
+    fn eval_callback(&self, queue: &JobQueue, task: &ImageTask) -> EvalCallback {
+        let file_name = extract_filename(&task.input_path);
+        let mut progress =
+            ImageProgress::new(queue.id(), file_name, queue.finished(), queue.total());
+        let app = self.app.clone();
+        Box::new(move |percent| {
+            if let Some(event) = progress.update(percent)
+                && let Err(e) = app.emit(IMAGE_PROGRESS_EVENT, event)
+            {
+                warn!("Failed to emit {IMAGE_PROGRESS_EVENT}: {e}");
+            }
+        })
+    }
 
-    fn emit_progress(
+    /// Records a task's result and emits its progress event.
+    fn complete(
         &self,
-        completed: usize,
-        total: usize,
-        job_start: Instant,
+        queue: &JobQueue,
+        clock: &RunClock,
+        index: usize,
         task: &ImageTask,
         result: &OptimizationResult,
     ) {
-        let percentage = (completed * 100) / total;
-        let is_final = completed == total;
-        let file_name = extract_filename(&task.input_path).to_string();
-        let saved_kb = result.saved_bytes as f64 / 1024.0;
+        queue.complete(index, result);
+        let completed = queue.finished();
+        let job_total = queue.total();
+        let status = if completed == job_total {
+            ProgressStatus::Complete
+        } else {
+            ProgressStatus::Processing
+        };
+        let item = item_progress(task, result);
+        debug!("{}", item.formatted_message);
+        let event = clock.event(queue.id(), completed, job_total, status, Some(item));
+        self.emit(PROGRESS_EVENT, event);
+    }
 
-        let formatted_msg = format!(
-            "{file_name} optimized ({saved_kb:.2} KB saved / {:.0}% compression)",
-            result.compression_ratio
+    /// Emits the job-finished event with a summary of every task in `queue`.
+    pub fn emit_job_finished(&self, queue: &JobQueue, clock: &RunClock) {
+        let summary = JobSummary::new(&queue.results(), clock);
+        debug!(
+            "Job {} summary: {}/{} succeeded, {} bytes saved in {:.2}s",
+            queue.id(),
+            summary.succeeded,
+            summary.total_tasks,
+            summary.saved_bytes,
+            summary.duration_seconds
         );
+        let event = JobFinishedEvent { job_id: queue.id().to_string(), summary };
+        self.emit(JOB_FINISHED_EVENT, event);
+    }
+
+    fn emit<T: serde::Serialize + Clone>(&self, event: &str, payload: T) {
+        if let Err(e) = self.app.emit(event, payload) {
+            warn!("Failed to emit {event}: {e}");
+        }
+    }
+}
 
-        debug!("{formatted_msg}");
+// ── Progress payloads ─────────────────────────────────────────────────────────────────
 
-        let mut metadata = serde_json::json!({
-            "formattedMessage": formatted_msg,
-            "fileName": file_name,
-            "originalSize": result.original_size,
-            "optimizedSize": result.optimized_size,
-            "savedBytes": result.saved_bytes,
-            "compressionRatio": format!("{:.2}", result.compression_ratio),
-        });
+/// Describes one finished task for its progress event.
+fn item_progress(task: &ImageTask, result: &OptimizationResult) -> ItemProgress {
+    let file_name = extract_filename(&task.input_path).to_string();
+    let saved_kb = result.saved_bytes as f64 / 1024.0;
 
-        if is_final {
-            let duration_secs = job_start.elapsed().as_secs_f64();
-            metadata["totalDuration"] = serde_json::json!(format!("{duration_secs:.2}"));
+    let formatted_message = match result.status {
+        OptimizationStatus::CopiedOriginal => {
+            format!("{file_name} kept original (optimized file was not smaller)")
+        }
+        OptimizationStatus::Skipped => {
+            format!("{file_name} skipped (optimized file was not smaller)")
+        }
+        OptimizationStatus::SkippedExisting => {
+            format!("{file_name} skipped (output already exists)")
+        }
+        OptimizationStatus::Cached => {
+            format!("{file_name} unchanged since last run (cached)")
+        }
+        OptimizationStatus::AlreadyOptimized => {
+            format!("{file_name} skipped (already optimized)")
         }
+        OptimizationStatus::Failed => format!(
+            "{file_name} failed: {}",
+            result.error.as_deref().unwrap_or("unknown error")
+        ),
+        OptimizationStatus::Optimized => format!(
+            "{file_name} optimized ({saved_kb:.2} KB saved / {:.0}% compression)",
+            result.compression_ratio
+        ),
+    };
 
-        let payload = serde_json::json!({
-            "completedTasks": completed,
-            "totalTasks": total,
-            "progressPercentage": percentage,
-            "status": if is_final { "complete" } else { "processing" },
-            "metadata": metadata,
-        });
+    ItemProgress {
+        file_name,
+        formatted_message,
+        result_status: result.status,
+        original_size: result.original_size,
+        optimized_size: result.optimized_size,
+        saved_bytes: result.saved_bytes,
+        compression_ratio: result.compression_ratio,
+        error: result.error.clone(),
+        error_code: result.error_code,
+        error_detail: result.error_detail.clone(),
+        timings: result.timings,
+    }
+}
 
-        let _ = self.app.emit("image_optimization_progress", payload);
+// ── Blocking processing (runs on tokio's blocking thread pool) ────────────────────────
+
+/// Answers a task from the result cache or prior-output detection, or
+/// optimizes it and records the result.
+///
+/// Cache problems never fail a task; they only cost a re-encode.
+fn optimize_cached(
+    task: &ImageTask,
+    cache: &ResultCache,
+    limits: &ResourceLimits,
+    on_eval: EvalCallback,
+) -> OptimizerResult<OptimizationResult> {
+    let settings = &task.settings;
+    let input_hash = if settings.use_cache || settings.skip_optimized {
+        file_sha256(&task.input_path)
+            .inspect_err(|e| {
+                debug!("Cannot hash '{}': {e}", extract_filename(&task.input_path));
+            })
+            .ok()
+    } else {
+        None
+    };
+
+    let key = match (&input_hash, settings.use_cache) {
+        (Some(hash), true) => cache.key(task, hash).ok(),
+        _ => None,
+    };
+    if let Some(key) = &key
+        && let Some(hit) = cache.lookup(key, task)
+    {
+        debug!("'{}' unchanged, reusing {}", extract_filename(&task.input_path), hit.optimized_path);
+        return Ok(hit);
     }
 
-    fn emit_error_progress(&self, completed: usize, total: usize, task: &ImageTask, error: &str) {
-        let percentage = (completed * 100) / total;
-        let file_name = extract_filename(&task.input_path).to_string();
-
-        let payload = serde_json::json!({
-            "completedTasks": completed,
-            "totalTasks": total,
-            "progressPercentage": percentage,
-            "status": if completed == total { "complete" } else { "error" },
-            "metadata": {
-                "fileName": file_name,
-                "error": error,
-            },
-        });
-
-        let _ = self.app.emit("image_optimization_progress", payload);
+    if settings.skip_optimized
+        && let Some(reason) = prior_output_reason(task, input_hash.as_deref(), cache)
+    {
+        debug!("'{}' skipped: {reason}", extract_filename(&task.input_path));
+        return already_optimized_result(task);
     }
-}
 
-// ── Blocking processing (runs on tokio's blocking thread pool) ────────────────────────
+    let result = optimize_single(task, limits, on_eval)?;
+    cache.remember_output(task, &result);
+    if let Some(key) = key {
+        cache.record(key, task, &result);
+    }
+    Ok(result)
+}
 
 /// Optimises one task synchronously — dispatches to SVG or raster pipeline.
-fn optimize_single(task: &ImageTask) -> OptimizerResult<OptimizationResult> {
-    let format = format_from_extension(&task.input_path)?;
-    if format == ImageFormat::SVG {
-        return optimize_svg(task);
+///
+/// The file size is checked against `limits` before anything is read;
+/// `on_eval` receives encoding progress of raster images.
+fn optimize_single(
+    task: &ImageTask,
+    limits: &ResourceLimits,
+    on_eval: EvalCallback,
+) -> OptimizerResult<OptimizationResult> {
+    if let Ok(meta) = std::fs::metadata(&task.input_path) {
+        limits.check_file_size(meta.len()).map_err(|e| e.with_path(&task.input_path))?;
     }
-    optimize_raster(task)
+    let detected = detect_format(&task.input_path)?;
+    if detected.extension_mismatch() {
+        warn!(
+            "'{}' is a {} file despite its extension",
+            extract_filename(&task.input_path),
+            detected.format.name()
+        );
+    }
+    let result = if detected.format == ImageFormat::SVG {
+        optimize_svg(task, &detected)
+    } else {
+        optimize_raster(task, &detected, limits, on_eval)
+    };
+    result.map_err(|e| e.with_path(&task.input_path))
 }
 
 // ── SVG optimization ──────────────────────────────────────────────────────────────────
@@ -183,15 +426,22 @@ fn quality_to_precision(quality: u32) -> u8 {
 ///
 /// Parses the SVG into an AST, runs the default SVGO-equivalent jobs with
 /// float precision derived from the quality slider, and writes the result.
-fn optimize_svg(task: &ImageTask) -> OptimizerResult<OptimizationResult> {
+fn optimize_svg(
+    task: &ImageTask,
+    detected: &DetectedFormat,
+) -> OptimizerResult<OptimizationResult> {
     use oxvg_ast::{parse::roxmltree::parse, serialize::Node as _, visitor::Info};
     use oxvg_optimiser::Jobs;
 
     let input_path = &task.input_path;
     let precision = quality_to_precision(task.settings.quality.global);
 
-    let svg_content = std::fs::read_to_string(input_path)
-        .map_err(|e| OptimizerError::processing(format!("Cannot read SVG file: {e}")))?;
+    let mut timings = PhaseTimings::default();
+    let phase = Instant::now();
+    let svg_content = std::fs::read_to_string(input_path).map_err(|e| {
+        OptimizerError::io("Cannot read SVG file", &e).with_operation("read")
+    })?;
+    timings.decode_ms = elapsed_ms(phase);
 
     let original_size = svg_content.len() as u64;
 
@@ -205,25 +455,43 @@ fn optimize_svg(task: &ImageTask) -> OptimizerResult<OptimizationResult> {
     let mut jobs = Jobs::default();
     jobs.extend(&precision_overrides);
 
+    let phase = Instant::now();
     let optimized_svg = parse(&svg_content, |dom, allocator| {
         jobs.run(dom, &Info::new(allocator))
             .map_err(|e| e.to_string())?;
         dom.serialize().map_err(|e| e.to_string())
     })
-    .map_err(|e| OptimizerError::processing(format!("SVG parsing failed: {e}")))?
-    .map_err(|e| OptimizerError::processing(format!("SVG optimization failed: {e}")))?;
+    .map_err(|e| {
+        OptimizerError::new(ErrorCode::SvgParseFailed, "SVG parsing failed")
+            .with_detail(e.to_string())
+            .with_operation("parse")
+            .with_format("svg")
+    })?
+    .map_err(|e| {
+        OptimizerError::new(ErrorCode::SvgOptimizeFailed, "SVG optimization failed")
+            .with_detail(e)
+            .with_operation("optimize")
+            .with_format("svg")
+    })?;
+
+    let resolved_path = task.resolved_output_path()?;
+    let Some(output_path) =
+        resolve_collision(resolved_path.clone(), input_path, task.settings.collision_policy)?
+    else {
+        return Ok(existing_output_result(task, resolved_path, original_size));
+    };
+    let staged = StagedOutput::new(&output_path)?
+        .with_collision_policy(task.settings.collision_policy);
 
-    let output_path = &task.output_path;
-    if let Some(parent) = Path::new(output_path).parent() {
-        std::fs::create_dir_all(parent).map_err(|e| {
-            OptimizerError::processing(format!("Cannot create output directory: {e}"))
-        })?;
-    }
+    std::fs::write(staged.temp_path(), &optimized_svg)
+        .map_err(|e| OptimizerError::io("Cannot write optimized SVG", &e).with_operation("write"))?;
+    timings.encode_ms = elapsed_ms(phase);
 
-    std::fs::write(output_path, &optimized_svg)
-        .map_err(|e| OptimizerError::processing(format!("Cannot write optimized SVG: {e}")))?;
+    let phase = Instant::now();
+    let outcome = finalize_output(&task.settings, input_path, staged, output_path, original_size)?;
+    timings.write_ms = elapsed_ms(phase);
 
-    let optimized_size = optimized_svg.len() as u64;
+    let optimized_size = outcome.size;
     let saved_bytes = original_size as i64 - optimized_size as i64;
     let compression_ratio = if original_size > 0 {
         saved_bytes as f64 / original_size as f64 * 100.0
@@ -240,38 +508,145 @@ fn optimize_svg(task: &ImageTask) -> OptimizerResult<OptimizationResult> {
 
     Ok(OptimizationResult {
         original_path: input_path.clone(),
-        optimized_path: output_path.clone(),
+        optimized_path: outcome.path,
         original_size,
         optimized_size,
         success: true,
+        status: outcome.status,
         error: None,
+        error_code: None,
+        error_detail: None,
         saved_bytes,
         compression_ratio,
+        timings,
+        details: ImageDetails {
+            input_format: Some("svg".to_string()),
+            extension_mismatch: detected.extension_mismatch(),
+            output_format: Some("svg".to_string()),
+            ..ImageDetails::default()
+        },
     })
 }
 
 // ── Raster image optimization ─────────────────────────────────────────────────────────
 
+/// Where a raster task's output goes.
+enum RasterTarget {
+    /// Resolved and staged before decoding
+    Fixed { output_path: String, staged: StagedOutput },
+    /// Chosen by `outputFormat: "auto"` once the candidates are encoded
+    Auto(Eligible),
+}
+
+/// Whether a source with `pages` pages is loaded whole under `settings`;
+/// otherwise only its first page is decoded.
+fn loads_every_page(settings: &ImageSettings, pages: u64) -> bool {
+    pages > 1
+        && is_auto(settings)
+        && settings.auto.candidates.iter().any(|f| supports_animation(&normalize_format(f)))
+}
+
+/// Checks a lazily opened image against `limits`; reads only the header.
+///
+/// Heights are per frame, also when every frame of an animation is loaded,
+/// and only the frames actually loaded count towards the page limit.
+fn check_header(image: &VipsImage, limits: &ResourceLimits) -> OptimizerResult<()> {
+    let page_height = image.get_page_height().max(1);
+    limits.check_header(
+        image.get_width().max(0) as u32,
+        page_height as u32,
+        (image.get_height() / page_height).max(1) as u32,
+    )
+}
+
 /// Optimises one raster image task synchronously via libvips.
-fn optimize_raster(task: &ImageTask) -> OptimizerResult<OptimizationResult> {
+///
+/// `detected` comes from content sniffing; when that was inconclusive the
+/// libvips loader that opened the file has the final word on the input
+/// format reported in the result.
+///
+/// Dimensions and page count are checked against `limits` from the header,
+/// before any pixels are decoded.
+///
+/// libvips decodes lazily, so decoding, resizing, and encoding all happen
+/// while the output is written; `on_eval` follows that write. With
+/// `outputFormat: "auto"` the processed image is first decoded into memory,
+/// which `on_eval` follows instead, and every candidate is encoded from there.
+fn optimize_raster(
+    task: &ImageTask,
+    detected: &DetectedFormat,
+    limits: &ResourceLimits,
+    on_eval: EvalCallback,
+) -> OptimizerResult<OptimizationResult> {
     let input_path = &task.input_path;
     let settings = &task.settings;
 
     let original_size = std::fs::metadata(input_path)
         .map(|m| m.len())
-        .map_err(|e| OptimizerError::processing(format!("Cannot read input file: {e}")))?;
-
-    let output_format = resolve_output_format(input_path, &settings.output_format)?;
-    let output_path = ensure_correct_extension(&task.output_path, input_path, &output_format);
-
-    if let Some(parent) = Path::new(&output_path).parent() {
-        std::fs::create_dir_all(parent).map_err(|e| {
-            OptimizerError::processing(format!("Cannot create output directory: {e}"))
-        })?;
-    }
+        .map_err(|e| OptimizerError::io("Cannot read input file", &e).with_operation("read"))?;
+
+    let original_format = detected.format.name().to_string();
+    let resolved_path = task.resolved_output_path()?;
+    let target = if is_auto(settings) {
+        let eligible = eligible_formats(input_path, settings)?;
+        // Any candidate's output counts, so skipped re-runs never decode
+        if settings.collision_policy == CollisionPolicy::Skip
+            && let Some(existing) = eligible
+                .formats
+                .iter()
+                .map(|f| ensure_correct_extension(&resolved_path, input_path, f))
+                .find(|p| Path::new(p).exists() && !same_path(p, input_path))
+        {
+            return Ok(existing_output_result(task, existing, original_size));
+        }
+        RasterTarget::Auto(eligible)
+    } else {
+        let Some(output_path) =
+            resolve_collision(resolved_path.clone(), input_path, settings.collision_policy)?
+        else {
+            return Ok(existing_output_result(task, resolved_path, original_size));
+        };
+        let staged =
+            StagedOutput::new(&output_path)?.with_collision_policy(settings.collision_policy);
+        RasterTarget::Fixed { output_path, staged }
+    };
+    // Until encoding picks one, the first eligible candidate stands in
+    let output_format = match &target {
+        RasterTarget::Fixed { .. } => target_format(input_path, settings)?,
+        RasterTarget::Auto(eligible) => eligible.formats[0].clone(),
+    };
+    // Animations keep every frame only where a candidate can store them
+    let load_path = match &target {
+        RasterTarget::Auto(eligible) if eligible.animated => format!("{input_path}[n=-1]"),
+        _ => input_path.clone(),
+    };
 
+    let mut timings = PhaseTimings::default();
+    let mut details = ImageDetails {
+        input_format: Some(original_format.clone()),
+        extension_mismatch: detected.extension_mismatch(),
+        output_format: Some(output_format.clone()),
+        quality: Some(effective_quality(&settings.quality, &output_format)),
+        ..ImageDetails::default()
+    };
+    let mut loader = None;
     let image = if needs_resize(&settings.resize) {
-        let img = load_and_resize(input_path, &settings.resize)?;
+        let phase = Instant::now();
+        let mark = ErrorMark::now();
+        let source = VipsImage::new_from_file(input_path).map_err(|_| {
+            mark.error(ErrorCode::DecodeFailed, format!("Failed to probe '{input_path}'"))
+                .with_operation("probe")
+        })?;
+        check_header(&source, limits)?;
+        details.source_width = Some(source.get_width() as u32);
+        details.source_height = Some(source.get_page_height() as u32);
+        details.source_bit_depth = Some(bit_depth(&source));
+        // vips_thumbnail turns the image upright from its EXIF orientation
+        details.rotated = source.get_int("orientation").is_some_and(|o| o > 1);
+        loader = source.get_string("vips-loader");
+        drop(source);
+        let img = load_and_resize(&load_path, &settings.resize)?;
+        timings.resize_ms = elapsed_ms(phase);
         debug!(
             "Loaded+resized '{}': {}×{}",
             extract_filename(input_path),
@@ -280,26 +655,115 @@ fn optimize_raster(task: &ImageTask) -> OptimizerResult<OptimizationResult> {
         );
         img
     } else {
-        let img = VipsImage::new_from_file_access(input_path, Access::Sequential, false)
-            .map_err(|_| OptimizerError::processing(format!(
-                "Failed to load '{input_path}': {}",
-                super::vips_error_buffer_string()
-            )))?;
+        let phase = Instant::now();
+        let mark = ErrorMark::now();
+        let img = VipsImage::new_from_file_access(&load_path, Access::Sequential, false)
+            .map_err(|_| {
+                mark.error(ErrorCode::DecodeFailed, format!("Failed to load '{input_path}'"))
+                    .with_operation("load")
+            })?;
+        check_header(&img, limits)?;
+        timings.decode_ms = elapsed_ms(phase);
+        details.source_width = Some(img.get_width() as u32);
+        details.source_height = Some(img.get_page_height() as u32);
+        details.source_bit_depth = Some(bit_depth(&img));
+        loader = img.get_string("vips-loader");
         debug!(
             "Loaded '{}': {}×{}",
             extract_filename(input_path),
             img.get_width(),
             img.get_height()
         );
-        apply_resize(img, &settings.resize)?
+        let phase = Instant::now();
+        let img = apply_resize(img, &settings.resize)?;
+        timings.resize_ms = elapsed_ms(phase);
+        img
     };
 
-    save_image_as(&image, &output_path, &output_format, &settings.quality)?;
+    if detected.source == FormatSource::Extension
+        && let Some(format) = loader.as_deref().and_then(format_from_loader)
+        && format != detected.format
+    {
+        debug!("libvips loaded '{}' as {}", extract_filename(input_path), format.name());
+        details.input_format = Some(format.name().to_string());
+        details.extension_mismatch = true;
+    }
 
-    let optimized_size = std::fs::metadata(&output_path)
-        .map(|m| m.len())
-        .unwrap_or(0);
+    details.output_width = Some(image.get_width() as u32);
+    details.output_height = Some(image.get_page_height() as u32);
+    details.resized = was_resized(&details);
+
+    let phase = Instant::now();
+    let (output_path, staged) = match target {
+        RasterTarget::Fixed { output_path, staged } => {
+            let image = apply_alpha_policy(image, &output_format, &settings.alpha)?;
+            image.set_progress_callback(on_eval);
+            let temp_path = staged.temp_path().to_str().ok_or_else(|| {
+                OptimizerError::new(ErrorCode::Io, "Output path is not valid UTF-8")
+                    .with_path(staged.target_path())
+                    .with_operation("write")
+            })?;
+            let output_bits = save_image_as(
+                &image,
+                temp_path,
+                &output_format,
+                &settings.quality,
+                &settings.advanced,
+            )?;
+            details.output_bit_depth = Some(output_bits);
+            (output_path, staged)
+        }
+        RasterTarget::Auto(eligible) => {
+            // Every candidate reads the image, so it is decoded once into memory
+            image.set_progress_callback(on_eval);
+            let mark = ErrorMark::now();
+            let image = VipsImage::image_copy_memory(image).map_err(|_| {
+                mark.error(ErrorCode::DecodeFailed, format!("Failed to decode '{input_path}'"))
+                    .with_operation("decode")
+            })?;
+            let choice = choose_format(&image, &eligible.formats, settings)?;
+            drop(image);
+            details.output_format = Some(choice.format.clone());
+            details.quality = Some(effective_quality(&settings.quality, &choice.format));
+            details.output_bit_depth = Some(choice.bit_depth);
+            details.format_candidates = choice.candidates;
+
+            let chosen_path = ensure_correct_extension(&resolved_path, input_path, &choice.format);
+            let Some(output_path) =
+                resolve_collision(chosen_path.clone(), input_path, settings.collision_policy)?
+            else {
+                return Ok(existing_output_result(task, chosen_path, original_size));
+            };
+            let staged =
+                StagedOutput::new(&output_path)?.with_collision_policy(settings.collision_policy);
+            std::fs::write(staged.temp_path(), &choice.bytes).map_err(|e| {
+                OptimizerError::io("Cannot write optimized image", &e).with_operation("write")
+            })?;
+            (output_path, staged)
+        }
+    };
+    // Both arms release the source before an in-place rename replaces it.
+    timings.encode_ms = elapsed_ms(phase);
+
+    let fallback_path = ensure_correct_extension(&output_path, input_path, &original_format);
+    let phase = Instant::now();
+    let outcome = finalize_output(settings, input_path, staged, fallback_path, original_size)?;
+    timings.write_ms = elapsed_ms(phase);
+
+    if outcome.status == OptimizationStatus::CopiedOriginal {
+        details = ImageDetails {
+            output_format: details.input_format.clone(),
+            output_width: details.source_width,
+            output_height: details.source_height,
+            output_bit_depth: details.source_bit_depth,
+            quality: None,
+            resized: false,
+            rotated: false,
+            ..details
+        };
+    }
 
+    let optimized_size = outcome.size;
     let saved_bytes = original_size as i64 - optimized_size as i64;
     let compression_ratio = if original_size > 0 {
         saved_bytes as f64 / original_size as f64 * 100.0
@@ -316,67 +780,168 @@ fn optimize_raster(task: &ImageTask) -> OptimizerResult<OptimizationResult> {
 
     Ok(OptimizationResult {
         original_path: input_path.clone(),
-        optimized_path: output_path,
+        optimized_path: outcome.path,
         original_size,
         optimized_size,
         success: true,
+        status: outcome.status,
         error: None,
+        error_code: None,
+        error_detail: None,
         saved_bytes,
         compression_ratio,
+        timings,
+        details,
     })
 }
 
 // ── Helpers ───────────────────────────────────────────────────────────────────────────
 
-/// Resolves "original" to the actual input format and normalizes "jpg" → "jpeg".
-fn resolve_output_format(input_path: &str, requested: &str) -> OptimizerResult<String> {
-    if requested == "original" {
-        let ext = Path::new(input_path)
-            .extension()
-            .and_then(|e| e.to_str())
-            .ok_or_else(|| OptimizerError::format("Input file has no extension"))?;
-
-        return Ok(normalize_format(ext));
+/// Builds the result for a task that failed with `error`.
+fn failed_result(task: &ImageTask, error: &OptimizerError) -> OptimizationResult {
+    OptimizationResult {
+        original_path: task.input_path.clone(),
+        optimized_path: task.requested_output_path().to_string(),
+        original_size: std::fs::metadata(&task.input_path)
+            .map(|m| m.len())
+            .unwrap_or(0),
+        optimized_size: 0,
+        success: false,
+        status: OptimizationStatus::Failed,
+        error: Some(error.message()),
+        error_code: Some(error.code()),
+        error_detail: error.detail(),
+        saved_bytes: 0,
+        compression_ratio: 0.0,
+        timings: PhaseTimings::default(),
+        details: ImageDetails::default(),
     }
-
-    Ok(normalize_format(requested))
 }
 
-/// Returns `output_path` with the extension corrected to match `format`.
+/// Builds the result for a task skipped because its output already exists.
 ///
-/// When the output format differs from the extension already on `output_path`
-/// (e.g. converting foo.jpg → webp), the extension is replaced.
-fn ensure_correct_extension(output_path: &str, input_path: &str, format: &str) -> String {
-    let new_ext = match format {
-        "jpeg" => "jpg",
-        other => other,
+/// Sizes describe the existing file at `optimized_path` so batch totals
+/// still reflect what is on disk.
+fn existing_output_result(
+    task: &ImageTask,
+    optimized_path: String,
+    original_size: u64,
+) -> OptimizationResult {
+    let optimized_size = std::fs::metadata(&optimized_path)
+        .map(|m| m.len())
+        .unwrap_or(0);
+    let saved_bytes = original_size as i64 - optimized_size as i64;
+    let compression_ratio = if original_size > 0 {
+        saved_bytes as f64 / original_size as f64 * 100.0
+    } else {
+        0.0
     };
 
-    let path = Path::new(output_path);
-    let current_ext = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+    debug!("'{}' skipped, {} already exists", extract_filename(&task.input_path), optimized_path);
+
+    OptimizationResult {
+        original_path: task.input_path.clone(),
+        optimized_path,
+        original_size,
+        optimized_size,
+        success: true,
+        status: OptimizationStatus::SkippedExisting,
+        error: None,
+        error_code: None,
+        error_detail: None,
+        saved_bytes,
+        compression_ratio,
+        timings: PhaseTimings::default(),
+        details: ImageDetails::default(),
+    }
+}
+
+/// Explains why `task`'s input should not be recompressed, if it shouldn't.
+///
+/// An input is left alone when its content matches an output written by an
+/// earlier run with the same settings, or when it is a JPEG headed for JPEG without resizing whose
+/// quantization tables suggest a quality at or below the target — another
+/// pass would only lose detail.
+fn prior_output_reason(
+    task: &ImageTask,
+    input_hash: Option<&str>,
+    cache: &ResultCache,
+) -> Option<String> {
+    if input_hash.is_some_and(|hash| cache.is_own_output(hash, task)) {
+        return Some("produced by an earlier run".to_string());
+    }
 
-    if normalize_format(&current_ext) == format {
-        return output_path.to_string();
+    let settings = &task.settings;
+    if detect_format(&task.input_path).ok()?.format != ImageFormat::JPEG
+        || resolve_output_format(&task.input_path, &settings.output_format).ok()? != "jpeg"
+        || needs_resize(&settings.resize)
+    {
+        return None;
     }
 
-    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
-    let parent = path.parent().unwrap_or(Path::new(""));
+    let target = effective_quality(&settings.quality, "jpeg");
+    let estimated = estimate_jpeg_quality(&task.input_path).ok().flatten()?;
+    (estimated <= target)
+        .then(|| format!("JPEG quality ~{estimated} is already at or below target {target}"))
+}
 
-    let stem = if stem.is_empty() {
-        Path::new(input_path)
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("output")
-    } else {
-        stem
-    };
+/// Builds the result for an input that was left untouched by prior-output detection.
+///
+/// A task with its own output path gets a copy of the source there, under
+/// the source's real extension and the task's collision policy, so the
+/// output folder stays complete. In-place tasks keep the file as it is.
+fn already_optimized_result(task: &ImageTask) -> OptimizerResult<OptimizationResult> {
+    let input_path = &task.input_path;
+    let size = std::fs::metadata(input_path)
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    let mut optimized_path = input_path.clone();
+    if !task.settings.in_place {
+        let format = detect_format(input_path)?.format.name();
+        let target = ensure_correct_extension(&task.resolved_output_path()?, input_path, format);
+        match resolve_collision(target.clone(), input_path, task.settings.collision_policy)? {
+            Some(path) if !same_path(&path, input_path) => {
+                let copy = StagedOutput::new(&path)?
+                    .with_collision_policy(task.settings.collision_policy);
+                std::fs::copy(input_path, copy.temp_path()).map_err(|e| {
+                    OptimizerError::io("Cannot copy original to output", &e)
+                        .with_operation("write")
+                })?;
+                optimized_path = copy.place(input_path, &task.settings.backup)?.unwrap_or(path);
+            }
+            Some(_) => {}
+            None => optimized_path = target,
+        }
+    }
+
+    Ok(OptimizationResult {
+        original_path: input_path.clone(),
+        optimized_path,
+        original_size: size,
+        optimized_size: size,
+        success: true,
+        status: OptimizationStatus::AlreadyOptimized,
+        error: None,
+        error_code: None,
+        error_detail: None,
+        saved_bytes: 0,
+        compression_ratio: 0.0,
+        timings: PhaseTimings::default(),
+        details: ImageDetails::default(),
+    })
+}
+
+/// Whether the output dimensions differ from the source's, allowing for a
+/// rotation that swapped width and height.
+fn was_resized(details: &ImageDetails) -> bool {
+    let source = (details.source_width, details.source_height);
+    let output = (details.output_width, details.output_height);
+    let turned = (source.1, source.0);
+    source != output && !(details.rotated && turned == output)
+}
 
-    parent
-        .join(format!("{stem}.{new_ext}"))
-        .to_string_lossy()
-        .to_string()
+/// Milliseconds elapsed since `start`.
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
 }
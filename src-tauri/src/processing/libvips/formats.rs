@@ -1,30 +1,64 @@
 // src-tauri/src/processing/libvips/formats.rs
 
 //! Maps ImageSettings quality values to libvips format-specific save options.
+//!
+//! Images are saved to a file, or encoded into memory with the same options
+//! when `outputFormat: "auto"` compares candidates.
+//!
+//! Defaults are chosen from the effective quality; any field set in the
+//! format's [`AdvancedSettings`] block overrides the matching default.
+//!
+//! High-bit-depth sources keep their depth where the format allows (16-bit
+//! PNG, 10-bit AVIF by default) together with their ICC profile, so
+//! wide-gamut masters keep their primaries. Other targets get a dithered
+//! 8-bit image.
+//!
+//! HDR is not preserved: libvips neither reads nor writes CICP (AVIF `nclx`)
+//! colour information, so a PQ or HLG source is written without its
+//! transfer characteristics and displays as SDR, as if its code values were
+//! sRGB-like. Only an HDR transfer described by the ICC profile survives.
 
 use libvips::ops::{
     self,
-    ForeignHeifCompression, ForeignPngFilter, ForeignSubsample,
-    ForeignKeep,
+    ForeignHeifCompression, ForeignHeifEncoder, ForeignPngFilter, ForeignSubsample,
+    ForeignKeep, ForeignWebpPreset,
+};
+use crate::core::{
+    AdvancedSettings, AvifAdvanced, AvifEncoder, ChromaSubsampling, Compression, JpegAdvanced,
+    PngAdvanced, PngFilter, QualitySettings, WebpAdvanced, WebpPreset,
 };
-use crate::core::QualitySettings;
 use crate::utils::OptimizerError;
+use crate::utils::error::ErrorCode;
 use libvips::VipsImage;
-use super::vips_error_buffer_string;
+use super::depth::{bit_depth, dither_to_8bit};
+use super::ErrorMark;
 
 type Result<T> = std::result::Result<T, OptimizerError>;
 
+/// Moves every field of a file saver's options into the buffer saver's
+/// options struct, which libvips generates with identical fields.
+macro_rules! buffer_options {
+    ($opts:expr, $buffer:ident { $($field:ident),* $(,)? }) => {{
+        let opts = $opts;
+        ops::$buffer { $($field: opts.$field),* }
+    }};
+}
+
 // ── Default quality constants ─────────────────────────────────────────────────────────
 
 const PNG_COMPRESSION: i32 = 7; // 0-9
 const PNG_EFFORT: i32 = 4;
 const WEBP_EFFORT: i32 = 4;
 const AVIF_EFFORT: i32 = 2;
+const JPEG_QUANT_TABLE: i32 = 3;
+/// AVIF depth for high-bit-depth sources; 12 bits needs AV1's Professional
+/// profile, which few decoders support.
+const AVIF_HIGH_BIT_DEPTH: u32 = 10;
 
 // ── Effective quality helpers ──────────────────────────────────────────────────────────
 
 /// Returns the effective quality for a given format, respecting per-format overrides.
-fn effective_quality(quality: &QualitySettings, format: &str) -> u32 {
+pub(crate) fn effective_quality(quality: &QualitySettings, format: &str) -> u32 {
     let per_format = match format {
         "jpeg" => quality.jpeg,
         "png" => quality.png,
@@ -35,127 +69,393 @@ fn effective_quality(quality: &QualitySettings, format: &str) -> u32 {
     per_format.unwrap_or(quality.global)
 }
 
-/// Returns `true` when the effective quality for a format is 100 (lossless).
-fn is_lossless(quality: &QualitySettings, format: &str) -> bool {
-    effective_quality(quality, format) == 100
+/// Returns the compression mode for a format: the explicit per-format mode,
+/// or else the historical mapping from quality (100 is lossless; AVIF at 90
+/// or above is near-lossless).
+fn compression_mode(quality: &QualitySettings, format: &str) -> Compression {
+    let explicit = match format {
+        "jpeg" => quality.mode.jpeg,
+        "png" => quality.mode.png,
+        "webp" => quality.mode.webp,
+        "avif" => quality.mode.avif,
+        _ => None,
+    };
+    if let Some(mode) = explicit {
+        return mode;
+    }
+    match effective_quality(quality, format) {
+        100 => Compression::Lossless,
+        90.. if format == "avif" => Compression::NearLossless,
+        _ => Compression::Lossy,
+    }
 }
 
 // ── Format save functions ──────────────────────────────────────────────────────────────
 
-/// Saves `image` as JPEG with mozjpeg-equivalent settings.
+/// JPEG save options with mozjpeg-equivalent settings.
 ///
-/// When quality == 100: enables trellis quantisation and overshoot deringing
-/// for maximum compression quality; disables progressive scans (expensive at
-/// high quality with diminishing returns). Otherwise: progressive scans
-/// enabled, trellis/deringing off for faster encoding.
-pub fn save_jpeg(image: &VipsImage, output_path: &str, quality: &QualitySettings) -> Result<()> {
-    let q = effective_quality(quality, "jpeg") as i32;
-    let lossless = is_lossless(quality, "jpeg");
-
-    let opts = ops::JpegsaveOptions {
-        q,
+/// JPEG is always lossy, and the quality number only sets `Q`. Scan layout,
+/// trellis quantisation, and deringing come from [`JpegAdvanced`]: baseline
+/// output with trellis and deringing off unless set. Progressive output also
+/// optimises its scan order.
+fn jpeg_options(quality: &QualitySettings, advanced: &JpegAdvanced) -> ops::JpegsaveOptions {
+    let progressive = advanced.progressive == Some(true);
+
+    ops::JpegsaveOptions {
+        q: effective_quality(quality, "jpeg") as i32,
         optimize_coding: true,
-        // Progressive scan optimization is expensive at high quality; skip it in lossless mode
-        optimize_scans: !lossless,
-        trellis_quant: lossless,
-        overshoot_deringing: lossless,
-        quant_table: 3,
+        interlace: progressive,
+        // Scan optimisation only applies to progressive output
+        optimize_scans: progressive,
+        trellis_quant: advanced.trellis.unwrap_or(false),
+        overshoot_deringing: advanced.deringing.unwrap_or(false),
+        quant_table: advanced.quant_table.map_or(JPEG_QUANT_TABLE, |t| t as i32),
         // Auto lets libvips pick 4:4:4 at high quality and 4:2:0 at lower quality
-        subsample_mode: ForeignSubsample::Auto,
+        subsample_mode: advanced.subsampling.map_or(ForeignSubsample::Auto, subsample),
         keep: ForeignKeep::None,
         ..ops::JpegsaveOptions::default()
-    };
+    }
+}
+
+/// Saves `image` as JPEG (see [`jpeg_options`]).
+pub fn save_jpeg(
+    image: &VipsImage,
+    output_path: &str,
+    quality: &QualitySettings,
+    advanced: &JpegAdvanced,
+) -> Result<()> {
+    let mark = ErrorMark::now();
+    ops::jpegsave_with_opts(image, output_path, &jpeg_options(quality, advanced))
+        .map_err(|_| mark.error(ErrorCode::EncodeFailed, "JPEG save failed"))
+}
+
+/// Returns `true` when PNG output is palette-quantised.
+///
+/// Asking for a palette size implies a palette, even in lossless mode.
+fn png_palette(quality: &QualitySettings, advanced: &PngAdvanced) -> bool {
+    advanced.palette.unwrap_or(
+        advanced.colors.is_some() || compression_mode(quality, "png") != Compression::Lossless,
+    )
+}
 
-    ops::jpegsave_with_opts(image, output_path, &opts)
-        .map_err(|_| OptimizerError::processing(format!("JPEG save failed: {}", vips_error_buffer_string())))
+/// Bits per sample written for `format` from a source of `source_bits`.
+///
+/// This is the `bitdepth` the encoder is given; for a palette PNG it is the
+/// bits per palette index, which `colors` can bring below 8.
+fn output_bit_depth(
+    format: &str,
+    source_bits: u32,
+    quality: &QualitySettings,
+    advanced: &AdvancedSettings,
+) -> u32 {
+    let high = source_bits > 8;
+    match format {
+        "png" if png_palette(quality, &advanced.png) => {
+            advanced.png.colors.map_or(8, palette_bitdepth)
+        }
+        "png" if high => 16,
+        "avif" => advanced.avif.bit_depth.unwrap_or(if high { AVIF_HIGH_BIT_DEPTH } else { 8 }),
+        _ => 8,
+    }
 }
 
-/// Saves `image` as PNG.
+/// Metadata kept in the output: the ICC profile for high-bit-depth output,
+/// so wide-gamut masters keep their colour, and nothing otherwise. CICP is
+/// not among what libvips can keep (see the module docs).
+fn keep_for(bit_depth: u32) -> ForeignKeep {
+    if bit_depth > 8 { ForeignKeep::Icc } else { ForeignKeep::None }
+}
+
+/// PNG save options for `bit_depth` bits per sample, from [`output_bit_depth`].
 ///
-/// When quality == 100: max deflate compression, max effort, adaptive row
-/// filtering for best file size. Otherwise: palette quantisation with fast
-/// defaults.
-pub fn save_png(image: &VipsImage, output_path: &str, quality: &QualitySettings) -> Result<()> {
+/// Lossless: max deflate compression, max effort, adaptive row filtering for
+/// best file size. Lossy: palette quantisation at the given quality with
+/// fast defaults; palettes are 8-bit unless `colors` needs fewer.
+fn png_options(
+    quality: &QualitySettings,
+    advanced: &PngAdvanced,
+    bit_depth: u32,
+) -> ops::PngsaveOptions {
     let q = effective_quality(quality, "png") as i32;
-    let lossless = is_lossless(quality, "png");
+    let lossless = compression_mode(quality, "png") == Compression::Lossless;
 
-    let opts = ops::PngsaveOptions {
+    let mut opts = ops::PngsaveOptions {
         compression: if lossless { 9 } else { PNG_COMPRESSION },
-        palette: !lossless,
+        palette: png_palette(quality, advanced),
         q,
         effort: if lossless { 10 } else { PNG_EFFORT },
-        filter: if lossless { ForeignPngFilter::All } else { ForeignPngFilter::None },
-        keep: ForeignKeep::None,
+        filter: match advanced.filter {
+            Some(filter) => png_filter(filter),
+            None if lossless => ForeignPngFilter::All,
+            None => ForeignPngFilter::None,
+        },
+        interlace: advanced.interlace.unwrap_or(false),
+        bitdepth: bit_depth as i32,
+        keep: keep_for(bit_depth),
         ..ops::PngsaveOptions::default()
     };
+    if let Some(dither) = advanced.dither {
+        opts.dither = dither;
+    }
+    opts
+}
 
-    ops::pngsave_with_opts(image, output_path, &opts)
-        .map_err(|_| OptimizerError::processing(format!("PNG save failed: {}", vips_error_buffer_string())))
+/// Saves `image` as PNG (see [`png_options`]).
+pub fn save_png(
+    image: &VipsImage,
+    output_path: &str,
+    quality: &QualitySettings,
+    advanced: &PngAdvanced,
+    bit_depth: u32,
+) -> Result<()> {
+    let mark = ErrorMark::now();
+    ops::pngsave_with_opts(image, output_path, &png_options(quality, advanced, bit_depth))
+        .map_err(|_| mark.error(ErrorCode::EncodeFailed, "PNG save failed"))
 }
 
-/// Saves `image` as WebP.
+/// WebP save options.
 ///
-/// When quality == 100: lossless mode with max effort and min-size
-/// optimization. Otherwise: lossy with fast defaults.
-pub fn save_webp(image: &VipsImage, output_path: &str, quality: &QualitySettings) -> Result<()> {
+/// Lossless: lossless mode with max effort and min-size optimization.
+/// Near-lossless: lossless mode with preprocessing whose strength follows
+/// the quality; the deprecated `nearLossless` option selects it when no mode
+/// is set. Lossy: lossy with fast defaults, including at q=100.
+fn webp_options(quality: &QualitySettings, advanced: &WebpAdvanced) -> ops::WebpsaveOptions {
     let q = effective_quality(quality, "webp") as i32;
-    let lossless = is_lossless(quality, "webp");
+    let mode = match (quality.mode.webp, advanced.near_lossless) {
+        (None, Some(true)) => Compression::NearLossless,
+        _ => compression_mode(quality, "webp"),
+    };
+    let lossless_coding = mode != Compression::Lossy;
 
-    let opts = ops::WebpsaveOptions {
+    ops::WebpsaveOptions {
         q,
-        lossless,
-        alpha_q: q,
-        effort: if lossless { 6 } else { WEBP_EFFORT },
-        min_size: lossless,
-        smart_subsample: false,
+        lossless: mode == Compression::Lossless,
+        preset: advanced.preset.map_or(ForeignWebpPreset::Default, webp_preset),
+        alpha_q: advanced.alpha_quality.map_or(q, |a| a as i32),
+        effort: advanced.effort.map_or(if lossless_coding { 6 } else { WEBP_EFFORT }, |e| e as i32),
+        min_size: lossless_coding,
+        smart_subsample: advanced.smart_subsample.unwrap_or(false),
+        near_lossless: mode == Compression::NearLossless,
         keep: ForeignKeep::None,
         ..ops::WebpsaveOptions::default()
-    };
+    }
+}
 
-    ops::webpsave_with_opts(image, output_path, &opts)
-        .map_err(|_| OptimizerError::processing(format!("WebP save failed: {}", vips_error_buffer_string())))
+/// Saves `image` as WebP (see [`webp_options`]).
+pub fn save_webp(
+    image: &VipsImage,
+    output_path: &str,
+    quality: &QualitySettings,
+    advanced: &WebpAdvanced,
+) -> Result<()> {
+    let mark = ErrorMark::now();
+    ops::webpsave_with_opts(image, output_path, &webp_options(quality, advanced))
+        .map_err(|_| mark.error(ErrorCode::EncodeFailed, "WebP save failed"))
 }
 
-/// Saves `image` as AVIF (AV1 via HEIF container).
+/// AVIF (AV1 via HEIF container) save options for `bit_depth` (8, 10, or
+/// 12) bits per sample.
 ///
-/// At quality 100 we use q=100 with 4:4:4 chroma, max effort (9), and no
-/// `lossless` flag. The `lossless` flag is avoided because AV1 lossless mode
-/// applies an internal RGB->YCbCr conversion that produces visible color
-/// shifts on some encoder builds (notably Windows aom/svt-av1 in libvips 8.18).
-pub fn save_avif(image: &VipsImage, output_path: &str, quality: &QualitySettings) -> Result<()> {
-    let q = effective_quality(quality, "avif") as i32;
-    let lossless = is_lossless(quality, "avif");
-    let near_lossless = q >= 90;
-
-    let opts = ops::HeifsaveOptions {
+/// Lossless uses q=100 with 4:4:4 chroma, max effort (9), and no `lossless`
+/// flag. The `lossless` flag is avoided because AV1 lossless mode applies an
+/// internal RGB->YCbCr conversion that produces visible color shifts on some
+/// encoder builds (notably Windows aom/svt-av1 in libvips 8.18).
+/// Near-lossless keeps 4:4:4 chroma at the given quality; lossy subsamples
+/// chroma to 4:2:0.
+fn avif_options(
+    quality: &QualitySettings,
+    advanced: &AvifAdvanced,
+    bit_depth: u32,
+) -> ops::HeifsaveOptions {
+    let mode = compression_mode(quality, "avif");
+    let lossless = mode == Compression::Lossless;
+    let q = if lossless { 100 } else { effective_quality(quality, "avif") as i32 };
+    let default_subsample =
+        if mode == Compression::Lossy { ForeignSubsample::On } else { ForeignSubsample::Off };
+
+    ops::HeifsaveOptions {
         q,
         lossless: false,
         compression: ForeignHeifCompression::Av1,
-        effort: if lossless { 9 } else { AVIF_EFFORT },
-        bitdepth: 8,
-        subsample_mode: if near_lossless { ForeignSubsample::Off } else { ForeignSubsample::On },
-        keep: ForeignKeep::None,
+        effort: advanced.effort.map_or(if lossless { 9 } else { AVIF_EFFORT }, |e| e as i32),
+        bitdepth: bit_depth as i32,
+        subsample_mode: advanced.subsampling.map_or(default_subsample, subsample),
+        encoder: advanced.encoder.map_or(ForeignHeifEncoder::Auto, avif_encoder),
+        keep: keep_for(bit_depth),
         ..ops::HeifsaveOptions::default()
-    };
+    }
+}
 
-    ops::heifsave_with_opts(image, output_path, &opts)
-        .map_err(|_| OptimizerError::processing(format!("AVIF save failed: {}", vips_error_buffer_string())))
+/// Saves `image` as AVIF (see [`avif_options`]).
+pub fn save_avif(
+    image: &VipsImage,
+    output_path: &str,
+    quality: &QualitySettings,
+    advanced: &AvifAdvanced,
+    bit_depth: u32,
+) -> Result<()> {
+    let mark = ErrorMark::now();
+    ops::heifsave_with_opts(image, output_path, &avif_options(quality, advanced, bit_depth))
+        .map_err(|_| mark.error(ErrorCode::EncodeFailed, "AVIF save failed"))
 }
 
-/// Dispatches to the correct format save function based on `format`.
+/// Dispatches to the correct format save function based on `format` and
+/// returns the bits per sample written.
 ///
-/// `format` must be one of: `"jpeg"`, `"png"`, `"webp"`, `"avif"`.
+/// `format` must be one of: `"jpeg"`, `"png"`, `"webp"`, `"avif"`. A
+/// high-bit-depth `image` is dithered to 8 bits only when the output
+/// cannot hold more.
 pub fn save_image_as(
     image: &VipsImage,
     output_path: &str,
     format: &str,
     quality: &QualitySettings,
-) -> Result<()> {
-    match format {
-        "jpeg" => save_jpeg(image, output_path, quality),
-        "png" => save_png(image, output_path, quality),
-        "webp" => save_webp(image, output_path, quality),
-        "avif" => save_avif(image, output_path, quality),
+    advanced: &AdvancedSettings,
+) -> Result<u32> {
+    with_output_depth(image, format, quality, advanced, |image, bits| match format {
+        "jpeg" => save_jpeg(image, output_path, quality, &advanced.jpeg),
+        "png" => save_png(image, output_path, quality, &advanced.png, bits),
+        "webp" => save_webp(image, output_path, quality, &advanced.webp),
+        "avif" => save_avif(image, output_path, quality, &advanced.avif, bits),
+        other => Err(OptimizerError::format(format!("Unsupported output format: {other}"))),
+    })
+    .map(|((), bits)| bits)
+}
+
+/// Encodes `image` as `format` into memory with the same options as
+/// [`save_image_as`], returning the encoded bytes and the bits per sample.
+pub fn encode_image_as(
+    image: &VipsImage,
+    format: &str,
+    quality: &QualitySettings,
+    advanced: &AdvancedSettings,
+) -> Result<(Vec<u8>, u32)> {
+    with_output_depth(image, format, quality, advanced, |image, bits| match format {
+        "jpeg" => {
+            let opts = buffer_options!(
+                jpeg_options(quality, &advanced.jpeg),
+                JpegsaveBufferOptions {
+                    q, optimize_coding, interlace, trellis_quant, overshoot_deringing,
+                    optimize_scans, quant_table, subsample_mode, restart_interval, keep,
+                    background, page_height, profile,
+                }
+            );
+            let mark = ErrorMark::now();
+            ops::jpegsave_buffer_with_opts(image, &opts)
+                .map_err(|_| mark.error(ErrorCode::EncodeFailed, "JPEG encode failed"))
+        }
+        "png" => {
+            let opts = buffer_options!(
+                png_options(quality, &advanced.png, bits),
+                PngsaveBufferOptions {
+                    compression, interlace, filter, palette, q, dither, bitdepth, effort, keep,
+                    background, page_height, profile,
+                }
+            );
+            let mark = ErrorMark::now();
+            ops::pngsave_buffer_with_opts(image, &opts)
+                .map_err(|_| mark.error(ErrorCode::EncodeFailed, "PNG encode failed"))
+        }
+        "webp" => {
+            let opts = buffer_options!(
+                webp_options(quality, &advanced.webp),
+                WebpsaveBufferOptions {
+                    q, lossless, preset, smart_subsample, near_lossless, alpha_q, min_size, kmin,
+                    kmax, effort, mixed, keep, background, page_height, profile,
+                }
+            );
+            let mark = ErrorMark::now();
+            ops::webpsave_buffer_with_opts(image, &opts)
+                .map_err(|_| mark.error(ErrorCode::EncodeFailed, "WebP encode failed"))
+        }
+        "avif" => {
+            let opts = buffer_options!(
+                avif_options(quality, &advanced.avif, bits),
+                HeifsaveBufferOptions {
+                    q, bitdepth, lossless, compression, effort, subsample_mode, encoder, keep,
+                    background, page_height, profile,
+                }
+            );
+            let mark = ErrorMark::now();
+            ops::heifsave_buffer_with_opts(image, &opts)
+                .map_err(|_| mark.error(ErrorCode::EncodeFailed, "AVIF encode failed"))
+        }
         other => Err(OptimizerError::format(format!("Unsupported output format: {other}"))),
+    })
+}
+
+/// Runs `encode` with `image` at the bit depth `format` is written with,
+/// dithering a high-bit-depth image first when the output holds 8 bits or
+/// fewer.
+fn with_output_depth<T>(
+    image: &VipsImage,
+    format: &str,
+    quality: &QualitySettings,
+    advanced: &AdvancedSettings,
+    encode: impl FnOnce(&VipsImage, u32) -> Result<T>,
+) -> Result<(T, u32)> {
+    let source_bits = bit_depth(image);
+    let bits = output_bit_depth(format, source_bits, quality, advanced);
+    let dithered;
+    let image = if source_bits > 8 && bits <= 8 {
+        dithered = dither_to_8bit(image)?;
+        &dithered
+    } else {
+        image
+    };
+
+    encode(image, bits)
+        .map(|encoded| (encoded, bits))
+        .map_err(|e| e.with_operation("encode").with_format(format))
+}
+
+// ── Advanced option mapping ────────────────────────────────────────────────────────────
+
+fn subsample(mode: ChromaSubsampling) -> ForeignSubsample {
+    match mode {
+        ChromaSubsampling::Auto => ForeignSubsample::Auto,
+        ChromaSubsampling::On => ForeignSubsample::On,
+        ChromaSubsampling::Off => ForeignSubsample::Off,
+    }
+}
+
+fn png_filter(filter: PngFilter) -> ForeignPngFilter {
+    match filter {
+        PngFilter::None => ForeignPngFilter::None,
+        PngFilter::Sub => ForeignPngFilter::Sub,
+        PngFilter::Up => ForeignPngFilter::Up,
+        PngFilter::Avg => ForeignPngFilter::Avg,
+        PngFilter::Paeth => ForeignPngFilter::Paeth,
+        PngFilter::All => ForeignPngFilter::All,
+    }
+}
+
+fn webp_preset(preset: WebpPreset) -> ForeignWebpPreset {
+    match preset {
+        WebpPreset::Default => ForeignWebpPreset::Default,
+        WebpPreset::Picture => ForeignWebpPreset::Picture,
+        WebpPreset::Photo => ForeignWebpPreset::Photo,
+        WebpPreset::Drawing => ForeignWebpPreset::Drawing,
+        WebpPreset::Icon => ForeignWebpPreset::Icon,
+        WebpPreset::Text => ForeignWebpPreset::Text,
+    }
+}
+
+fn avif_encoder(encoder: AvifEncoder) -> ForeignHeifEncoder {
+    match encoder {
+        AvifEncoder::Auto => ForeignHeifEncoder::Auto,
+        AvifEncoder::Aom => ForeignHeifEncoder::Aom,
+        AvifEncoder::Rav1e => ForeignHeifEncoder::Rav1E,
+        AvifEncoder::Svt => ForeignHeifEncoder::Svt,
+    }
+}
+
+/// Smallest PNG palette bit depth holding `colors` entries.
+fn palette_bitdepth(colors: u32) -> u32 {
+    match colors {
+        0..=2 => 1,
+        3..=4 => 2,
+        5..=16 => 4,
+        _ => 8,
     }
 }
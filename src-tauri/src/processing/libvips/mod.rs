@@ -6,26 +6,83 @@
 //!
 //! - [`NativeExecutor`]: Drives batch processing and emits Tauri progress events.
 //! - [`resize`]: Maps `ResizeSettings` resize modes to `ops::thumbnail_image_with_opts`.
-//! - [`formats`]: Maps `QualitySettings` to format-specific `ops::*save_with_opts` calls.
+//! - [`formats`]: Maps `QualitySettings` and `AdvancedSettings` to format-specific
+//!   `ops::*save_with_opts` and in-memory `ops::*save_buffer_with_opts` calls.
+//! - [`probe`]: Header-only inspection that flags unreadable inputs before a batch.
+//! - [`depth`]: Bit-depth detection and dithered reduction to 8 bits.
+//! - [`alpha`]: Flattens, rejects, or re-targets transparency the output format cannot store.
+//! - [`auto`]: Picks the smallest eligible format per image for `outputFormat: "auto"`.
 
+mod alpha;
+mod auto;
+mod depth;
 mod executor;
 mod formats;
+mod probe;
 mod resize;
 
+pub use alpha::target_format;
 pub use executor::NativeExecutor;
+pub use probe::{ImageProbe, probe_image};
+pub use resize::{fit_dimensions, output_dimensions};
+pub(crate) use formats::effective_quality;
 
 use std::ffi::CStr;
 
-/// Reads the global libvips error buffer for diagnostic messages.
-pub(crate) fn vips_error_buffer_string() -> String {
+use crate::utils::OptimizerError;
+use crate::utils::error::ErrorCode;
+
+/// libvips stops appending once its error buffer holds this many bytes
+/// (`VIPS_MAX_ERROR`), so it is emptied before it gets there.
+const VIPS_ERROR_BUFFER_LIMIT: usize = 8 * 1024;
+
+/// Length of the libvips error buffer before a call that may fail.
+///
+/// The buffer is shared by every thread, so it is not cleared per failure:
+/// that would drop messages other images' failures have yet to read.
+/// Instead the length is recorded before the call, and
+/// [`ErrorMark::error`] keeps everything appended since, so a message that
+/// spans several lines stays whole. Lines another thread appends between the
+/// mark and the failure are included too.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ErrorMark(usize);
+
+impl ErrorMark {
+    /// Records the current end of the libvips error buffer.
+    pub(crate) fn now() -> Self {
+        Self(vips_error_buffer_bytes().len())
+    }
+
+    /// Builds the error for the call that failed after this mark, with the
+    /// libvips messages it appended as the detail.
+    ///
+    /// The buffer is only emptied once it nears libvips' size limit. Loader
+    /// messages about unknown formats override `code` with
+    /// [`ErrorCode::UnsupportedFormat`].
+    pub(crate) fn error(self, code: ErrorCode, msg: impl Into<String>) -> OptimizerError {
+        let buffer = vips_error_buffer_bytes();
+        if buffer.len() >= VIPS_ERROR_BUFFER_LIMIT {
+            unsafe { libvips::bindings::vips_error_clear() };
+        }
+        // Another thread emptied the buffer since the mark; all of it is new
+        let appended = buffer.get(self.0..).unwrap_or(&buffer);
+        let detail = String::from_utf8_lossy(appended).trim().to_string();
+        let detail = if detail.is_empty() { "unknown error".to_string() } else { detail };
+
+        let unsupported = detail.contains("not a known file format")
+            || detail.contains("not in a known format");
+        let code = if unsupported { ErrorCode::UnsupportedFormat } else { code };
+        OptimizerError::new(code, msg).with_detail(detail)
+    }
+}
+
+/// Raw contents of the libvips error buffer.
+fn vips_error_buffer_bytes() -> Vec<u8> {
     unsafe {
         let ptr = libvips::bindings::vips_error_buffer();
         if ptr.is_null() {
-            return "unknown error".to_string();
+            return Vec::new();
         }
-        CStr::from_ptr(ptr)
-            .to_string_lossy()
-            .trim()
-            .to_string()
+        CStr::from_ptr(ptr).to_bytes().to_vec()
     }
 }
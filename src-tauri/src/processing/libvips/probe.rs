@@ -0,0 +1,181 @@
+// src-tauri/src/processing/libvips/probe.rs
+
+//! Header-only inspection of inputs before a batch starts.
+//!
+//! [`probe_image`] opens a file without decoding pixels (libvips reads only
+//! the header until pixels are requested) and checks the container for
+//! truncation, so corrupt, truncated, and oversized inputs are flagged with
+//! a coded error instead of failing mid-batch with a raw libvips message.
+//! "Oversized" means over the configured [`ResourceLimits`].
+//!
+//! [`probe_header`] is the pre-flight variant: it leaves SVG parsing to the
+//! SVG pipeline, which reads and parses the file anyway.
+
+use serde::Serialize;
+
+use libvips::VipsImage;
+use libvips::ops::BandFormat;
+
+use crate::core::ResourceLimits;
+use crate::utils::container::is_truncated;
+use crate::utils::error::ErrorCode;
+use crate::utils::{ImageFormat, OptimizerError, detect_format};
+
+use super::ErrorMark;
+
+/// What a header-only open revealed about one input.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageProbe {
+    pub path: String,
+    pub file_size: u64,
+    /// Format detected from content, e.g. `png`
+    pub format: Option<String>,
+    pub extension_mismatch: bool,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub bands: Option<u32>,
+    /// Sample type, e.g. `uchar` for 8-bit or `ushort` for 16-bit
+    pub band_format: Option<String>,
+    /// Frames or pages; 1 for still images
+    pub pages: Option<u32>,
+    pub has_alpha: bool,
+    pub has_icc: bool,
+    /// EXIF orientation (1–8); `None` when absent
+    pub orientation: Option<u32>,
+    /// Bytes needed to hold the first page decoded; an animation loaded whole
+    /// needs `pages` times as much
+    pub estimated_memory: Option<u64>,
+    /// Why the file cannot be processed; `None` when it can
+    pub error: Option<OptimizerError>,
+}
+
+/// Probes the file at `path`. Never fails; problems are reported in `error`.
+pub fn probe_image(path: &str, limits: &ResourceLimits) -> ImageProbe {
+    probe(path, limits, true)
+}
+
+/// Like [`probe_image`], but an SVG is only checked for size and
+/// truncation, not parsed.
+pub(crate) fn probe_header(path: &str, limits: &ResourceLimits) -> ImageProbe {
+    probe(path, limits, false)
+}
+
+fn probe(path: &str, limits: &ResourceLimits, parse_svg: bool) -> ImageProbe {
+    let mut probe = ImageProbe {
+        path: path.to_string(),
+        file_size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        format: None,
+        extension_mismatch: false,
+        width: None,
+        height: None,
+        bands: None,
+        band_format: None,
+        pages: None,
+        has_alpha: false,
+        has_icc: false,
+        orientation: None,
+        estimated_memory: None,
+        error: None,
+    };
+    if let Err(e) = inspect(path, limits, parse_svg, &mut probe) {
+        probe.error = Some(e.with_path(path).with_operation("probe"));
+    }
+    probe
+}
+
+fn inspect(
+    path: &str,
+    limits: &ResourceLimits,
+    parse_svg: bool,
+    probe: &mut ImageProbe,
+) -> Result<(), OptimizerError> {
+    let detected = detect_format(path)?;
+    probe.format = Some(detected.format.name().to_string());
+    probe.extension_mismatch = detected.extension_mismatch();
+
+    if probe.file_size == 0 {
+        return Err(OptimizerError::new(ErrorCode::Truncated, "File is empty"));
+    }
+    limits.check_file_size(probe.file_size)?;
+
+    let truncated = match is_truncated(path, detected.format) {
+        Ok(truncated) => truncated,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => true,
+        Err(e) => return Err(OptimizerError::io("Cannot read file", &e)),
+    };
+    if truncated {
+        return Err(OptimizerError::new(ErrorCode::Truncated, "File is truncated")
+            .with_format(detected.format.name()));
+    }
+
+    if detected.format == ImageFormat::SVG {
+        if !parse_svg {
+            return Ok(());
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| OptimizerError::io("Cannot read SVG file", &e))?;
+        oxvg_ast::parse::roxmltree::parse(&content, |_, _| ()).map_err(|e| {
+            OptimizerError::new(ErrorCode::SvgParseFailed, "SVG parsing failed")
+                .with_detail(e.to_string())
+                .with_format("svg")
+        })?;
+        return Ok(());
+    }
+
+    let mark = ErrorMark::now();
+    let image = VipsImage::new_from_file(path)
+        .map_err(|_| mark.error(ErrorCode::DecodeFailed, "Cannot read image header"))?;
+
+    let width = image.get_width().max(0) as u64;
+    let height = image.get_height().max(0) as u64;
+    let bands = image.get_bands().max(0) as u64;
+    let pages = image.get_n_pages().max(1) as u64;
+    let band_format = image.get_format().ok();
+
+    probe.width = Some(width as u32);
+    probe.height = Some(height as u32);
+    probe.bands = Some(bands as u32);
+    probe.band_format = band_format.map(band_format_name).map(str::to_string);
+    probe.pages = Some(pages as u32);
+    probe.has_alpha = image.image_hasalpha();
+    probe.has_icc = image.has_field("icc-profile-data");
+    probe.orientation = image.get_int("orientation").map(|o| o as u32);
+
+    let sample_bytes = band_format.map(sample_size).unwrap_or(1);
+    probe.estimated_memory = Some(width * height * bands * sample_bytes);
+
+    if width == 0 || height == 0 {
+        return Err(OptimizerError::new(ErrorCode::DecodeFailed, "Image has no pixels"));
+    }
+    // Stills decode one page; the executor checks animations loaded whole
+    limits.check_header(width as u32, height as u32, 1)
+}
+
+/// Bytes per sample for a libvips band format.
+pub(super) fn sample_size(format: BandFormat) -> u64 {
+    match format {
+        BandFormat::Uchar | BandFormat::Char | BandFormat::Notset | BandFormat::Last => 1,
+        BandFormat::Ushort | BandFormat::Short => 2,
+        BandFormat::Uint | BandFormat::Int | BandFormat::Float => 4,
+        BandFormat::Complex | BandFormat::Double => 8,
+        BandFormat::Dpcomplex => 16,
+    }
+}
+
+/// libvips' own name for a band format.
+fn band_format_name(format: BandFormat) -> &'static str {
+    match format {
+        BandFormat::Uchar => "uchar",
+        BandFormat::Char => "char",
+        BandFormat::Ushort => "ushort",
+        BandFormat::Short => "short",
+        BandFormat::Uint => "uint",
+        BandFormat::Int => "int",
+        BandFormat::Float => "float",
+        BandFormat::Complex => "complex",
+        BandFormat::Double => "double",
+        BandFormat::Dpcomplex => "dpcomplex",
+        BandFormat::Notset | BandFormat::Last => "notset",
+    }
+}
@@ -5,7 +5,8 @@
 use libvips::{ops, VipsImage};
 use crate::core::ResizeSettings;
 use crate::utils::OptimizerError;
-use super::vips_error_buffer_string;
+use crate::utils::error::ErrorCode;
+use super::ErrorMark;
 
 type Result<T> = std::result::Result<T, OptimizerError>;
 
@@ -15,24 +16,60 @@ pub fn needs_resize(settings: &ResizeSettings) -> bool {
     settings.mode != "none" && matches!(settings.size, Some(s) if s > 0)
 }
 
+/// Predicts the output size of a resize from the image header alone.
+pub fn output_dimensions(path: &str, settings: &ResizeSettings) -> Result<(i32, i32)> {
+    let mark = ErrorMark::now();
+    let probe = VipsImage::new_from_file(path).map_err(|_| probe_error(mark, path))?;
+    Ok(fit_dimensions(probe.get_width(), probe.get_height(), settings))
+}
+
+/// Computes the size `vips_thumbnail` produces for a `width`×`height` source.
+///
+/// Mirrors the bounding boxes built by [`load_and_resize`]: the image is
+/// scaled to fit inside the box with `Size::Down`, so it is never enlarged.
+pub fn fit_dimensions(width: i32, height: i32, settings: &ResizeSettings) -> (i32, i32) {
+    if !needs_resize(settings) || width <= 0 || height <= 0 {
+        return (width, height);
+    }
+
+    let size = settings.size.unwrap_or(0) as i32;
+    let (box_w, box_h) = match settings.mode.as_str() {
+        "width" => (size, height),
+        "height" => (width, size),
+        "longest" if width >= height => (size, height),
+        "longest" => (width, size),
+        "shortest" if width <= height => (size, height),
+        "shortest" => (width, size),
+        _ => (width, height),
+    };
+
+    let scale = (box_w as f64 / width as f64)
+        .min(box_h as f64 / height as f64)
+        .min(1.0);
+    (
+        ((width as f64 * scale).round() as i32).max(1),
+        ((height as f64 * scale).round() as i32).max(1),
+    )
+}
+
 /// Loads and resizes an image from `path` in one step.
 ///
 /// Uses the file-based `vips_thumbnail` which enables **shrink-on-load**:
 /// for JPEG, libjpeg can skip decoding most DCT coefficients when
 /// downsizing by integer factors (2x, 4x, 8x), making large-image
 /// resizes significantly faster than loading first and resizing second.
+/// `path` may carry loader options, such as `[n=-1]` to load every frame.
 pub fn load_and_resize(path: &str, settings: &ResizeSettings) -> Result<VipsImage> {
     let size = settings.size.unwrap_or(0) as i32;
     if size <= 0 {
-        return Err(OptimizerError::processing("No target size for resize".to_string()));
+        return Err(OptimizerError::new(ErrorCode::InvalidSettings, "No target size for resize"));
     }
 
-    let probe = VipsImage::new_from_file(path)
-        .map_err(|_| OptimizerError::processing(format!(
-            "Failed to probe '{}': {}", path, vips_error_buffer_string()
-        )))?;
+    let mark = ErrorMark::now();
+    let probe = VipsImage::new_from_file(path).map_err(|_| probe_error(mark, path))?;
     let orig_w = probe.get_width();
-    let orig_h = probe.get_height();
+    // Frame height, so animations loaded with every page fit per frame
+    let orig_h = probe.get_page_height();
 
     match settings.mode.as_str() {
         "width" => thumbnail_file(path, size, orig_h, "width"),
@@ -51,9 +88,10 @@ pub fn load_and_resize(path: &str, settings: &ResizeSettings) -> Result<VipsImag
                 thumbnail_file(path, orig_w, size, "shortest")
             }
         }
-        unknown => Err(OptimizerError::processing(format!(
-            "Unknown resize mode: {unknown}"
-        ))),
+        unknown => Err(OptimizerError::new(
+            ErrorCode::InvalidSettings,
+            format!("Unknown resize mode: {unknown}"),
+        )),
     }
 }
 
@@ -92,9 +130,10 @@ pub fn apply_resize(image: VipsImage, settings: &ResizeSettings) -> Result<VipsI
                 thumbnail_image(&image, orig_w, size, "shortest")
             }
         }
-        unknown => Err(OptimizerError::processing(format!(
-            "Unknown resize mode: {unknown}"
-        ))),
+        unknown => Err(OptimizerError::new(
+            ErrorCode::InvalidSettings,
+            format!("Unknown resize mode: {unknown}"),
+        )),
     }
 }
 
@@ -110,11 +149,12 @@ fn thumbnail_file(path: &str, target_w: i32, target_h: i32, mode: &str) -> Resul
         ..ThumbnailOptions::default()
     };
 
+    let mark = ErrorMark::now();
     ops::thumbnail_with_opts(path, target_w, &opts)
-        .map_err(|_| OptimizerError::processing(format!(
-            "Resize ({mode}) failed: {}",
-            vips_error_buffer_string()
-        )))
+        .map_err(|_| {
+            mark.error(ErrorCode::ResizeFailed, format!("Resize ({mode}) failed"))
+                .with_operation("resize")
+        })
 }
 
 /// Image-based thumbnail for already-loaded images.
@@ -128,10 +168,17 @@ fn thumbnail_image(image: &VipsImage, target_w: i32, target_h: i32, mode: &str)
         ..ThumbnailImageOptions::default()
     };
 
+    let mark = ErrorMark::now();
     ops::thumbnail_image_with_opts(image, target_w, &opts)
-        .map_err(|_| OptimizerError::processing(format!(
-            "Resize ({mode}) failed: {}",
-            vips_error_buffer_string()
-        )))
+        .map_err(|_| {
+            mark.error(ErrorCode::ResizeFailed, format!("Resize ({mode}) failed"))
+                .with_operation("resize")
+        })
 }
 
+/// Error for a header that libvips cannot read.
+fn probe_error(mark: ErrorMark, path: &str) -> OptimizerError {
+    mark.error(ErrorCode::DecodeFailed, format!("Failed to probe '{path}'"))
+        .with_path(path)
+        .with_operation("probe")
+}
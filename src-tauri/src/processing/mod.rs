@@ -1,5 +1,15 @@
 //! Image processing modules.
 //!
 //! - [`libvips`]: Native image processing via vendored Rust-to-libvips bindings.
+//! - [`output`]: Atomic output staging, collision handling, and size policy enforcement.
+//! - [`progress`]: Typed progress and job-finished event payloads.
+//! - [`report`]: Batch summary reports exported as JSON, CSV, or Markdown.
+//! - [`template`]: Output path templates for tasks without an explicit output path.
+//! - [`watch`]: Watch-folder mode that optimizes new images as they settle.
 
 pub mod libvips;
+pub mod output;
+pub mod progress;
+pub mod report;
+pub mod template;
+pub mod watch;
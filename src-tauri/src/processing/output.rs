@@ -0,0 +1,386 @@
+// src-tauri/src/processing/output.rs
+
+//! Post-encode output handling shared by the raster and SVG pipelines.
+//!
+//! Encoders never write to their final path directly. They write into a
+//! [`StagedOutput`] — a hidden temp file in the destination directory — which
+//! is fsynced and atomically renamed into place once the size policy accepts
+//! it. A crash mid-write therefore leaves at most a stray temp file, never a
+//! truncated output or a clobbered original.
+//!
+//! Collision handling for existing outputs is resolved here too: once before
+//! a task starts encoding, so skipped tasks cost nothing, and again when the
+//! output is moved into place. The second check claims the name with a hard
+//! link, which fails atomically when it is taken, so concurrent jobs and
+//! watch batches never replace each other's outputs under `skip` or `rename`.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{debug, warn};
+
+use crate::core::{
+    BackupMode, BackupSettings, CollisionPolicy, ImageSettings, OptimizationStatus, SizePolicy,
+};
+use crate::utils::error::{ErrorCode, ValidationError};
+use crate::utils::{OptimizerError, OptimizerResult, extract_filename};
+
+/// Disambiguates temp files staged concurrently by the same process.
+static STAGE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Where a task's output ended up after the size policy ran.
+#[derive(Debug)]
+pub struct PolicyOutcome {
+    /// Action that was taken
+    pub status: OptimizationStatus,
+    /// Path of the file the user should pick up
+    pub path: String,
+    /// Size of that file in bytes
+    pub size: u64,
+}
+
+/// A temp file in the target's directory that becomes the target on [`commit`](Self::commit).
+///
+/// Dropping an uncommitted stage removes the temp file.
+pub struct StagedOutput {
+    temp: PathBuf,
+    target: PathBuf,
+    policy: CollisionPolicy,
+    committed: bool,
+}
+
+impl StagedOutput {
+    /// Reserves a temp path next to `target`, creating the directory if needed.
+    pub fn new(target: impl Into<PathBuf>) -> OptimizerResult<Self> {
+        let target = target.into();
+        let parent = target.parent().unwrap_or(Path::new("")).to_path_buf();
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(&parent).map_err(|e| {
+                OptimizerError::io("Cannot create output directory", &e)
+                    .with_path(parent.to_string_lossy())
+                    .with_operation("write")
+            })?;
+        }
+
+        let name = target
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("output");
+        let temp = parent.join(format!(
+            ".{name}.{}-{}.tmp",
+            std::process::id(),
+            STAGE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        Ok(Self { temp, target, policy: CollisionPolicy::Overwrite, committed: false })
+    }
+
+    /// Sets the policy [`place`](Self::place) applies when the target is taken.
+    pub fn with_collision_policy(mut self, policy: CollisionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Path encoders should write to.
+    pub fn temp_path(&self) -> &Path {
+        &self.temp
+    }
+
+    /// Final destination as a string.
+    pub fn target_path(&self) -> String {
+        self.target.to_string_lossy().to_string()
+    }
+
+    /// Size of the staged file in bytes.
+    pub fn size(&self) -> OptimizerResult<u64> {
+        std::fs::metadata(&self.temp)
+            .map(|m| m.len())
+            .map_err(|e| OptimizerError::io("Cannot read staged output", &e).with_operation("write"))
+    }
+
+    /// Flushes the staged file to disk and atomically renames it over the target.
+    ///
+    /// When the target is `source` itself (in-place mode), the original is
+    /// first preserved according to `backup`.
+    pub fn commit(mut self, source: &str, backup: &BackupSettings) -> OptimizerResult<()> {
+        File::open(&self.temp)
+            .and_then(|f| f.sync_all())
+            .map_err(|e| OptimizerError::io("Cannot flush staged output", &e).with_operation("write"))?;
+
+        if same_path(&self.target_path(), source) {
+            backup_original(source, backup)?;
+        }
+
+        std::fs::rename(&self.temp, &self.target)
+            .map_err(|e| {
+                OptimizerError::io("Cannot move output into place", &e)
+                    .with_path(self.target_path())
+                    .with_operation("write")
+            })?;
+        self.committed = true;
+
+        sync_parent_dir(&self.target);
+        Ok(())
+    }
+
+    /// Moves the staged file into place without replacing a file another run
+    /// created since the collision policy was first resolved.
+    ///
+    /// Returns the path written, or `None` when the policy skips because the
+    /// target was taken. `rename` claims the first free `<stem>-<n>.<ext>`.
+    /// In-place targets and `overwrite` go through [`commit`](Self::commit).
+    pub fn place(
+        mut self,
+        source: &str,
+        backup: &BackupSettings,
+    ) -> OptimizerResult<Option<String>> {
+        if self.policy == CollisionPolicy::Overwrite || same_path(&self.target_path(), source) {
+            let path = self.target_path();
+            self.commit(source, backup)?;
+            return Ok(Some(path));
+        }
+
+        File::open(&self.temp).and_then(|f| f.sync_all()).map_err(|e| {
+            OptimizerError::io("Cannot flush staged output", &e).with_operation("write")
+        })?;
+
+        let requested = self.target.clone();
+        let mut n = 0u32;
+        loop {
+            match claim(&self.temp, &self.target) {
+                Ok(()) => {
+                    self.committed = true;
+                    sync_parent_dir(&self.target);
+                    return Ok(Some(self.target_path()));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => match self.policy {
+                    CollisionPolicy::Skip => return Ok(None),
+                    CollisionPolicy::Rename => {
+                        n += 1;
+                        self.target = numbered_path(&requested, n);
+                    }
+                    _ => return Err(ValidationError::already_exists(self.target_path()).into()),
+                },
+                Err(e) => {
+                    return Err(OptimizerError::io("Cannot move output into place", &e)
+                        .with_path(self.target_path())
+                        .with_operation("write"));
+                }
+            }
+        }
+    }
+}
+
+/// Moves `temp` to `target` unless `target` exists.
+///
+/// A hard link makes the check and the move one atomic step. File systems
+/// without hard links fall back to checking first, then renaming.
+fn claim(temp: &Path, target: &Path) -> std::io::Result<()> {
+    match std::fs::hard_link(temp, target) {
+        Ok(()) => {
+            if let Err(e) = std::fs::remove_file(temp) {
+                warn!("Failed to remove temp file {}: {e}", temp.display());
+            }
+            Ok(())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Err(e),
+        Err(_) if target.exists() => Err(std::io::ErrorKind::AlreadyExists.into()),
+        Err(_) => std::fs::rename(temp, target),
+    }
+}
+
+impl Drop for StagedOutput {
+    fn drop(&mut self) {
+        if !self.committed
+            && let Err(e) = std::fs::remove_file(&self.temp)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!("Failed to remove temp file {}: {e}", self.temp.display());
+        }
+    }
+}
+
+/// Applies the collision policy to `target` before anything is encoded.
+///
+/// Returns `None` when the task should be skipped because the target exists.
+/// A target equal to the input (in-place mode) never counts as a collision.
+/// `rename` keeps the requested name here; [`StagedOutput::place`] picks the
+/// free one when the output is written, so the choice cannot go stale.
+pub fn resolve_collision(
+    target: String,
+    input_path: &str,
+    policy: CollisionPolicy,
+) -> OptimizerResult<Option<String>> {
+    if !Path::new(&target).exists() || same_path(&target, input_path) {
+        return Ok(Some(target));
+    }
+
+    match policy {
+        CollisionPolicy::Overwrite | CollisionPolicy::Rename => Ok(Some(target)),
+        CollisionPolicy::Skip => Ok(None),
+        CollisionPolicy::Fail => Err(ValidationError::already_exists(target).into()),
+    }
+}
+
+/// Returns `<stem>-<n>.<ext>` next to `target`.
+fn numbered_path(target: &Path, n: u32) -> PathBuf {
+    let parent = target.parent().unwrap_or(Path::new(""));
+    let stem = target.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    match target.extension().and_then(|e| e.to_str()) {
+        Some(ext) => parent.join(format!("{stem}-{n}.{ext}")),
+        None => parent.join(format!("{stem}-{n}")),
+    }
+}
+
+/// Returns `true` when `settings` accept an encoded output of `optimized_size` bytes.
+pub fn accepts_output(settings: &ImageSettings, original_size: u64, optimized_size: u64) -> bool {
+    match settings.size_policy {
+        SizePolicy::Always => true,
+        SizePolicy::CopyOriginalIfLarger | SizePolicy::SkipIfLarger => {
+            optimized_size <= original_size
+        }
+        SizePolicy::MinSavings => {
+            if original_size == 0 {
+                return false;
+            }
+            let saved = original_size as f64 - optimized_size as f64;
+            let min_percent = settings.min_savings_percent.unwrap_or(0.0);
+            saved / original_size as f64 * 100.0 >= min_percent
+        }
+    }
+}
+
+/// Applies the size policy to a staged output and moves the winner into place.
+///
+/// `fallback_path` is where the untouched source is copied when the policy
+/// falls back to the original. It only differs from the staged target when
+/// the task converts between formats, so the copied file keeps its real extension.
+pub fn finalize_output(
+    settings: &ImageSettings,
+    input_path: &str,
+    staged: StagedOutput,
+    fallback_path: String,
+    original_size: u64,
+) -> OptimizerResult<PolicyOutcome> {
+    let optimized_size = staged.size()?;
+
+    if accepts_output(settings, original_size, optimized_size) {
+        let target = staged.target_path();
+        return match staged.place(input_path, &settings.backup)? {
+            Some(path) => {
+                if path != target {
+                    debug!("'{}' exists, wrote {}", extract_filename(&target), path);
+                }
+                let size = optimized_size;
+                Ok(PolicyOutcome { status: OptimizationStatus::Optimized, path, size })
+            }
+            None => Ok(existing_outcome(target)),
+        };
+    }
+
+    debug!(
+        "'{}' → output {} bytes vs original {} bytes, applying {:?}",
+        extract_filename(input_path),
+        optimized_size,
+        original_size,
+        settings.size_policy
+    );
+    drop(staged);
+
+    if settings.size_policy == SizePolicy::SkipIfLarger {
+        return Ok(PolicyOutcome {
+            status: OptimizationStatus::Skipped,
+            path: input_path.to_string(),
+            size: original_size,
+        });
+    }
+
+    // In-place tasks already have the original at the fallback path.
+    let mut path = fallback_path;
+    if !same_path(&path, input_path) {
+        let copy = StagedOutput::new(&path)?.with_collision_policy(settings.collision_policy);
+        std::fs::copy(input_path, copy.temp_path()).map_err(|e| {
+            OptimizerError::io("Cannot copy original to output", &e).with_operation("write")
+        })?;
+        match copy.place(input_path, &settings.backup)? {
+            Some(placed) => path = placed,
+            None => return Ok(existing_outcome(path)),
+        }
+    }
+
+    Ok(PolicyOutcome {
+        status: OptimizationStatus::CopiedOriginal,
+        path,
+        size: original_size,
+    })
+}
+
+/// Outcome for a target another run wrote while this one was encoding.
+fn existing_outcome(path: String) -> PolicyOutcome {
+    let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    PolicyOutcome { status: OptimizationStatus::SkippedExisting, path, size }
+}
+
+/// Preserves `source` before an in-place write replaces it.
+///
+/// An existing backup is kept: after an earlier in-place run it holds the
+/// true original, while `source` is already that run's output.
+fn backup_original(source: &str, backup: &BackupSettings) -> OptimizerResult<()> {
+    let source_path = Path::new(source);
+    let backup_path = match backup.mode {
+        BackupMode::None => return Ok(()),
+        BackupMode::Bak => {
+            let mut name = source_path.as_os_str().to_owned();
+            name.push(".bak");
+            PathBuf::from(name)
+        }
+        BackupMode::Directory => {
+            let dir = backup.directory.as_deref().ok_or_else(|| {
+                OptimizerError::new(ErrorCode::InvalidSettings, "Backup directory is not configured")
+            })?;
+            let dir = source_path.parent().unwrap_or(Path::new("")).join(dir);
+            dir.join(source_path.file_name().unwrap_or_default())
+        }
+    };
+
+    if backup_path.exists() {
+        debug!("Keeping existing backup {}", backup_path.display());
+        return Ok(());
+    }
+
+    let staged = StagedOutput::new(&backup_path)?.with_collision_policy(CollisionPolicy::Skip);
+    std::fs::copy(source_path, staged.temp_path())
+        .map_err(|e| OptimizerError::io("Cannot back up original", &e).with_operation("backup"))?;
+    // Another run may have backed the same file up since the check above
+    if staged.place("", &BackupSettings::default())?.is_none() {
+        debug!("Keeping existing backup {}", backup_path.display());
+        return Ok(());
+    }
+
+    debug!("Backed up '{}' to {}", extract_filename(source), backup_path.display());
+    Ok(())
+}
+
+/// Persists the rename itself by syncing the containing directory.
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) {
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+}
+
+/// Directory handles cannot be synced on Windows; `MoveFileEx` is durable enough.
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) {}
+
+/// Compares two paths, resolving them when both exist on disk.
+pub(crate) fn same_path(a: &str, b: &str) -> bool {
+    match (Path::new(a).canonicalize(), Path::new(b).canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => Path::new(a) == Path::new(b),
+    }
+}
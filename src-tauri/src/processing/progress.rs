@@ -0,0 +1,297 @@
+// src-tauri/src/processing/progress.rs
+
+//! Typed payloads for the events the executor emits to the frontend.
+//!
+//! - `image_optimization_progress` ([`ProgressEvent`]): one per finished
+//!   image, plus one when a job pauses and one per pre-flight chunk.
+//!   `status` always describes the job (`checking`, `processing`, `paused`,
+//!   `complete`); the outcome of the image itself
+//!   is in `metadata.resultStatus`, so a failed last image still reports a
+//!   `complete` job.
+//! - `image_optimization_image_progress` ([`ImageProgressEvent`]): progress
+//!   within an image that takes a while, from libvips `eval` signals. At most
+//!   one every 250 ms per image, and none for images that finish sooner.
+//! - `image_optimization_finished` ([`JobFinishedEvent`]): once per job run,
+//!   carrying the summary of every task in the job.
+//!
+//! Every field keeps the same type in every event; numbers are never
+//! serialized as strings.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::core::{OptimizationResult, OptimizationStatus, PhaseTimings};
+use crate::utils::error::ErrorCode;
+
+/// Event name for per-image progress.
+pub const PROGRESS_EVENT: &str = "image_optimization_progress";
+
+/// Event name for progress within one image.
+pub const IMAGE_PROGRESS_EVENT: &str = "image_optimization_image_progress";
+
+/// Shortest gap between two image-progress events for one image.
+const IMAGE_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Event name for the end of a job run.
+pub const JOB_FINISHED_EVENT: &str = "image_optimization_finished";
+
+/// Job-level status reported with each progress event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProgressStatus {
+    /// Inputs are being checked before the first image is processed
+    Checking,
+    /// More images are left
+    Processing,
+    /// The job is held between images
+    Paused,
+    /// This event reports the job's last image
+    Complete,
+}
+
+/// Payload of `image_optimization_progress`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressEvent {
+    pub job_id: String,
+    /// Finished tasks across the whole job, including earlier runs
+    pub completed_tasks: usize,
+    pub total_tasks: usize,
+    pub progress_percentage: u32,
+    pub status: ProgressStatus,
+    /// Wall-clock seconds since this run started, excluding paused time
+    pub elapsed_seconds: f64,
+    /// Images per second in this run
+    pub throughput: f64,
+    /// Estimated seconds until the job finishes; `None` until measurable
+    pub eta_seconds: Option<f64>,
+    /// The image this event reports; `None` for `paused` events
+    pub metadata: Option<ItemProgress>,
+}
+
+/// Outcome of one image within a [`ProgressEvent`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemProgress {
+    pub file_name: String,
+    /// Human-readable one-line description of the outcome
+    pub formatted_message: String,
+    pub result_status: OptimizationStatus,
+    pub original_size: u64,
+    pub optimized_size: u64,
+    pub saved_bytes: i64,
+    pub compression_ratio: f64,
+    /// Failure reason when `resultStatus` is `failed`
+    pub error: Option<String>,
+    /// Stable code of the failure
+    pub error_code: Option<ErrorCode>,
+    /// Raw library or OS message behind the failure
+    pub error_detail: Option<String>,
+    pub timings: PhaseTimings,
+}
+
+/// Payload of `image_optimization_image_progress`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageProgressEvent {
+    pub job_id: String,
+    pub file_name: String,
+    /// How much of this image is decoded, processed, and encoded (0–100)
+    pub image_percentage: u32,
+    /// Finished tasks across the whole job, not counting this one
+    pub completed_tasks: usize,
+    pub total_tasks: usize,
+    /// Whole-job progress, counting this image's finished share
+    pub progress_percentage: u32,
+}
+
+/// Turns the libvips `eval` callbacks of one image into rate-limited
+/// [`ImageProgressEvent`]s.
+pub struct ImageProgress {
+    job_id: String,
+    file_name: String,
+    completed: usize,
+    total: usize,
+    last_emit: Instant,
+    last_percent: u32,
+}
+
+impl ImageProgress {
+    /// Tracks the image after the `completed` finished tasks of a `total`-task job.
+    pub fn new(job_id: &str, file_name: &str, completed: usize, total: usize) -> Self {
+        Self {
+            job_id: job_id.to_string(),
+            file_name: file_name.to_string(),
+            completed,
+            total,
+            last_emit: Instant::now(),
+            last_percent: 0,
+        }
+    }
+
+    /// Returns the event to emit for `percent`, or `None` when the last one
+    /// was too recent, nothing moved, or the image is done (its regular
+    /// progress event follows).
+    pub fn update(&mut self, percent: i32) -> Option<ImageProgressEvent> {
+        let percent = percent.clamp(0, 100) as u32;
+        if percent <= self.last_percent
+            || percent >= 100
+            || self.last_emit.elapsed() < IMAGE_PROGRESS_INTERVAL
+        {
+            return None;
+        }
+        self.last_emit = Instant::now();
+        self.last_percent = percent;
+
+        let progress_percentage = if self.total > 0 {
+            ((self.completed * 100 + percent as usize) / self.total) as u32
+        } else {
+            100
+        };
+        Some(ImageProgressEvent {
+            job_id: self.job_id.clone(),
+            file_name: self.file_name.clone(),
+            image_percentage: percent,
+            completed_tasks: self.completed,
+            total_tasks: self.total,
+            progress_percentage,
+        })
+    }
+}
+
+/// Payload of `image_optimization_finished`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobFinishedEvent {
+    pub job_id: String,
+    pub summary: JobSummary,
+}
+
+/// Totals over every task of a job.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobSummary {
+    pub total_tasks: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Number of tasks per result status
+    pub by_status: HashMap<OptimizationStatus, usize>,
+    /// Combined size of every successful task's input
+    pub original_bytes: u64,
+    /// Combined size of every successful task's output
+    pub optimized_bytes: u64,
+    pub saved_bytes: i64,
+    pub compression_ratio: f64,
+    /// Wall-clock seconds of this run, excluding paused time
+    pub duration_seconds: f64,
+    /// Images per second in this run
+    pub throughput: f64,
+}
+
+impl JobSummary {
+    /// Summarizes `results`; timing figures come from `clock`.
+    pub fn new(results: &[OptimizationResult], clock: &RunClock) -> Self {
+        let mut by_status = HashMap::new();
+        let mut original_bytes = 0;
+        let mut optimized_bytes = 0;
+        for result in results {
+            *by_status.entry(result.status).or_insert(0) += 1;
+            if result.success {
+                original_bytes += result.original_size;
+                optimized_bytes += result.optimized_size;
+            }
+        }
+
+        let failed = by_status.get(&OptimizationStatus::Failed).copied().unwrap_or(0);
+        let saved_bytes = original_bytes as i64 - optimized_bytes as i64;
+        let compression_ratio = if original_bytes > 0 {
+            saved_bytes as f64 / original_bytes as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        Self {
+            total_tasks: results.len(),
+            succeeded: results.len() - failed,
+            failed,
+            by_status,
+            original_bytes,
+            optimized_bytes,
+            saved_bytes,
+            compression_ratio,
+            duration_seconds: clock.elapsed().as_secs_f64(),
+            throughput: clock.throughput(results.len()),
+        }
+    }
+}
+
+/// Measures one run of a job for throughput and ETA.
+///
+/// Tasks finished before the run (e.g. before a restart) and time spent
+/// paused are excluded, so a resumed job does not report inflated rates.
+pub struct RunClock {
+    start: Instant,
+    done_at_start: usize,
+    paused_nanos: AtomicU64,
+}
+
+impl RunClock {
+    /// Starts the clock for a job that already has `done_at_start` finished tasks.
+    pub fn start(done_at_start: usize) -> Self {
+        Self {
+            start: Instant::now(),
+            done_at_start,
+            paused_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Excludes a pause from the measured time.
+    pub fn add_paused(&self, paused: Duration) {
+        self.paused_nanos
+            .fetch_add(paused.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Active (non-paused) time since the run started.
+    pub fn elapsed(&self) -> Duration {
+        let paused = Duration::from_nanos(self.paused_nanos.load(Ordering::Relaxed));
+        self.start.elapsed().saturating_sub(paused)
+    }
+
+    /// Images per second, given `completed` tasks across the whole job.
+    pub fn throughput(&self, completed: usize) -> f64 {
+        let secs = self.elapsed().as_secs_f64();
+        let done = completed.saturating_sub(self.done_at_start);
+        if secs > 0.0 { done as f64 / secs } else { 0.0 }
+    }
+
+    /// Seconds until `total` tasks are done at the current rate.
+    pub fn eta(&self, completed: usize, total: usize) -> Option<f64> {
+        let rate = self.throughput(completed);
+        (rate > 0.0).then(|| total.saturating_sub(completed) as f64 / rate)
+    }
+
+    /// Builds a progress event with the timing fields filled in.
+    pub fn event(
+        &self,
+        job_id: &str,
+        completed: usize,
+        total: usize,
+        status: ProgressStatus,
+        metadata: Option<ItemProgress>,
+    ) -> ProgressEvent {
+        ProgressEvent {
+            job_id: job_id.to_string(),
+            completed_tasks: completed,
+            total_tasks: total,
+            progress_percentage: if total > 0 { (completed * 100 / total) as u32 } else { 100 },
+            status,
+            elapsed_seconds: self.elapsed().as_secs_f64(),
+            throughput: self.throughput(completed),
+            eta_seconds: self.eta(completed, total),
+            metadata,
+        }
+    }
+}
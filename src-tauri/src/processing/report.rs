@@ -0,0 +1,462 @@
+// src-tauri/src/processing/report.rs
+
+//! Batch summary reports built from optimization results.
+//!
+//! [`Report::new`] tallies a job's results into totals, a per-format
+//! breakdown, the largest wins and losses, failures with their reasons, and
+//! phase timings. [`Report::render`] serializes it as JSON (the full report),
+//! CSV (one row per image), or Markdown (a readable summary).
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{BackupSettings, OptimizationResult, OptimizationStatus};
+use crate::processing::output::StagedOutput;
+use crate::utils::error::{ErrorCode, ValidationError};
+use crate::utils::{OptimizerError, OptimizerResult, normalize_format};
+
+/// How many entries the wins and losses lists keep.
+const TOP_ENTRIES: usize = 10;
+
+/// Output format of an exported report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReportFormat {
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl ReportFormat {
+    /// Picks the format from a file extension (`json`, `csv`, `md`, `markdown`).
+    pub fn from_path(path: &str) -> OptimizerResult<Self> {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase);
+        match ext.as_deref() {
+            Some("json") => Ok(Self::Json),
+            Some("csv") => Ok(Self::Csv),
+            Some("md" | "markdown") => Ok(Self::Markdown),
+            _ => Err(ValidationError::settings(format!(
+                "Cannot infer report format from '{path}'; use .json, .csv or .md"
+            ))
+            .into()),
+        }
+    }
+}
+
+/// Summary of a batch.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    /// Local time the report was built, RFC 3339
+    pub generated_at: String,
+    pub totals: ReportTotals,
+    /// One row per output format, largest savings first
+    pub formats: Vec<FormatBreakdown>,
+    /// Images that saved the most bytes
+    pub largest_wins: Vec<ReportEntry>,
+    /// Images whose output grew, largest growth first
+    pub largest_losses: Vec<ReportEntry>,
+    pub failures: Vec<ReportFailure>,
+    pub timing: ReportTiming,
+    /// Every result the report was built from
+    pub results: Vec<OptimizationResult>,
+}
+
+/// Batch-wide totals.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportTotals {
+    pub images: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub by_status: HashMap<OptimizationStatus, usize>,
+    /// Combined size of every successful image's input
+    pub original_bytes: u64,
+    /// Combined size of every successful image's output
+    pub optimized_bytes: u64,
+    pub saved_bytes: i64,
+    pub compression_ratio: f64,
+}
+
+/// Totals for one output format.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatBreakdown {
+    pub format: String,
+    pub images: usize,
+    pub original_bytes: u64,
+    pub optimized_bytes: u64,
+    pub saved_bytes: i64,
+    pub compression_ratio: f64,
+}
+
+/// One image in the wins or losses list.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportEntry {
+    pub original_path: String,
+    pub optimized_path: String,
+    pub original_size: u64,
+    pub optimized_size: u64,
+    pub saved_bytes: i64,
+    pub compression_ratio: f64,
+}
+
+/// A failed image and why it failed.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportFailure {
+    pub original_path: String,
+    pub error: String,
+    pub code: Option<ErrorCode>,
+    /// Raw library or OS message behind the failure
+    pub detail: Option<String>,
+}
+
+/// Processing time summed over all images.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportTiming {
+    pub decode_ms: f64,
+    pub resize_ms: f64,
+    pub encode_ms: f64,
+    pub write_ms: f64,
+    pub total_ms: f64,
+    /// Average processing time of the images that were actually processed
+    pub average_ms: f64,
+}
+
+impl Report {
+    /// Tallies `results` into a report.
+    pub fn new(results: &[OptimizationResult]) -> Self {
+        let succeeded: Vec<&OptimizationResult> = results.iter().filter(|r| r.success).collect();
+
+        let mut by_status = HashMap::new();
+        for result in results {
+            *by_status.entry(result.status).or_insert(0) += 1;
+        }
+
+        let original_bytes = succeeded.iter().map(|r| r.original_size).sum();
+        let optimized_bytes = succeeded.iter().map(|r| r.optimized_size).sum();
+        let totals = ReportTotals {
+            images: results.len(),
+            succeeded: succeeded.len(),
+            failed: results.len() - succeeded.len(),
+            by_status,
+            original_bytes,
+            optimized_bytes,
+            saved_bytes: original_bytes as i64 - optimized_bytes as i64,
+            compression_ratio: ratio(original_bytes, optimized_bytes),
+        };
+
+        let mut per_format: BTreeMap<String, (usize, u64, u64)> = BTreeMap::new();
+        for result in &succeeded {
+            let entry = per_format.entry(output_format(result)).or_default();
+            entry.0 += 1;
+            entry.1 += result.original_size;
+            entry.2 += result.optimized_size;
+        }
+        let mut formats: Vec<FormatBreakdown> = per_format
+            .into_iter()
+            .map(|(format, (images, original_bytes, optimized_bytes))| FormatBreakdown {
+                format,
+                images,
+                original_bytes,
+                optimized_bytes,
+                saved_bytes: original_bytes as i64 - optimized_bytes as i64,
+                compression_ratio: ratio(original_bytes, optimized_bytes),
+            })
+            .collect();
+        formats.sort_by(|a, b| b.saved_bytes.cmp(&a.saved_bytes));
+
+        let mut by_savings = succeeded.clone();
+        by_savings.sort_by(|a, b| b.saved_bytes.cmp(&a.saved_bytes));
+        let largest_wins = by_savings
+            .iter()
+            .filter(|r| r.saved_bytes > 0)
+            .take(TOP_ENTRIES)
+            .map(|r| ReportEntry::from(*r))
+            .collect();
+        let largest_losses = by_savings
+            .iter()
+            .rev()
+            .filter(|r| r.saved_bytes < 0)
+            .take(TOP_ENTRIES)
+            .map(|r| ReportEntry::from(*r))
+            .collect();
+
+        let failures = results
+            .iter()
+            .filter(|r| !r.success)
+            .map(|r| ReportFailure {
+                original_path: r.original_path.clone(),
+                error: r.error.clone().unwrap_or_else(|| "unknown error".to_string()),
+                code: r.error_code,
+                detail: r.error_detail.clone(),
+            })
+            .collect();
+
+        let decode_ms: f64 = results.iter().map(|r| r.timings.decode_ms).sum();
+        let resize_ms: f64 = results.iter().map(|r| r.timings.resize_ms).sum();
+        let encode_ms: f64 = results.iter().map(|r| r.timings.encode_ms).sum();
+        let write_ms: f64 = results.iter().map(|r| r.timings.write_ms).sum();
+        let total_ms: f64 = results.iter().map(|r| r.timings.total_ms()).sum();
+        let processed = results.iter().filter(|r| r.timings.total_ms() > 0.0).count();
+        let timing = ReportTiming {
+            decode_ms,
+            resize_ms,
+            encode_ms,
+            write_ms,
+            total_ms,
+            average_ms: if processed > 0 { total_ms / processed as f64 } else { 0.0 },
+        };
+
+        Self {
+            generated_at: chrono::Local::now().to_rfc3339(),
+            totals,
+            formats,
+            largest_wins,
+            largest_losses,
+            failures,
+            timing,
+            results: results.to_vec(),
+        }
+    }
+
+    /// Serializes the report in `format`.
+    pub fn render(&self, format: ReportFormat) -> OptimizerResult<String> {
+        match format {
+            ReportFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|e| OptimizerError::processing(format!("Cannot serialize report: {e}"))),
+            ReportFormat::Csv => Ok(self.to_csv()),
+            ReportFormat::Markdown => Ok(self.to_markdown()),
+        }
+    }
+
+    /// Renders the report and writes it atomically to `path`.
+    pub fn write(&self, path: &str, format: ReportFormat) -> OptimizerResult<()> {
+        let content = self.render(format)?;
+        let staged = StagedOutput::new(path)?;
+        std::fs::write(staged.temp_path(), content)
+            .map_err(|e| OptimizerError::io("Cannot write report", &e).with_path(path))?;
+        staged.commit("", &BackupSettings::default())
+    }
+
+    fn to_csv(&self) -> String {
+        let mut out = String::from(
+            "original_path,optimized_path,status,input_format,output_format,quality,\
+             source_width,source_height,output_width,output_height,original_size,optimized_size,\
+             saved_bytes,compression_ratio,decode_ms,resize_ms,encode_ms,write_ms,error_code,error,\
+             error_detail\n",
+        );
+        for r in &self.results {
+            let fields = [
+                csv_field(&r.original_path),
+                csv_field(&r.optimized_path),
+                csv_field(&status_name(r.status)),
+                csv_field(r.details.input_format.as_deref().unwrap_or("")),
+                csv_field(r.details.output_format.as_deref().unwrap_or("")),
+                optional(r.details.quality),
+                optional(r.details.source_width),
+                optional(r.details.source_height),
+                optional(r.details.output_width),
+                optional(r.details.output_height),
+                r.original_size.to_string(),
+                r.optimized_size.to_string(),
+                r.saved_bytes.to_string(),
+                format!("{:.2}", r.compression_ratio),
+                format!("{:.1}", r.timings.decode_ms),
+                format!("{:.1}", r.timings.resize_ms),
+                format!("{:.1}", r.timings.encode_ms),
+                format!("{:.1}", r.timings.write_ms),
+                r.error_code.map(code_name).unwrap_or_default(),
+                csv_field(r.error.as_deref().unwrap_or("")),
+                csv_field(r.error_detail.as_deref().unwrap_or("")),
+            ];
+            out.push_str(&fields.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    fn to_markdown(&self) -> String {
+        let t = &self.totals;
+        let mut md = String::new();
+        let _ = writeln!(md, "# Optimization report\n");
+        let _ = writeln!(md, "Generated {}\n", self.generated_at);
+
+        let _ = writeln!(md, "## Summary\n");
+        let _ = writeln!(md, "| | |\n|---|---|");
+        let _ = writeln!(md, "| Images | {} |", t.images);
+        let _ = writeln!(md, "| Succeeded | {} |", t.succeeded);
+        let _ = writeln!(md, "| Failed | {} |", t.failed);
+        let _ = writeln!(md, "| Original size | {} |", human_bytes(t.original_bytes as i64));
+        let _ = writeln!(md, "| Optimized size | {} |", human_bytes(t.optimized_bytes as i64));
+        let _ = writeln!(md, "| Saved | {} ({:.1}%) |", human_bytes(t.saved_bytes), t.compression_ratio);
+        let mut statuses: Vec<_> = t.by_status.iter().collect();
+        statuses.sort_by(|a, b| b.1.cmp(a.1));
+        for (status, count) in statuses {
+            let _ = writeln!(md, "| `{}` | {} |", status_name(*status), count);
+        }
+
+        if !self.formats.is_empty() {
+            let _ = writeln!(md, "\n## By format\n");
+            let _ = writeln!(md, "| Format | Images | Original | Optimized | Saved |");
+            let _ = writeln!(md, "|---|---:|---:|---:|---:|");
+            for f in &self.formats {
+                let _ = writeln!(
+                    md,
+                    "| {} | {} | {} | {} | {} ({:.1}%) |",
+                    f.format,
+                    f.images,
+                    human_bytes(f.original_bytes as i64),
+                    human_bytes(f.optimized_bytes as i64),
+                    human_bytes(f.saved_bytes),
+                    f.compression_ratio
+                );
+            }
+        }
+
+        for (title, entries) in [
+            ("Largest wins", &self.largest_wins),
+            ("Largest losses", &self.largest_losses),
+        ] {
+            if entries.is_empty() {
+                continue;
+            }
+            let _ = writeln!(md, "\n## {title}\n");
+            let _ = writeln!(md, "| File | Original | Optimized | Saved |");
+            let _ = writeln!(md, "|---|---:|---:|---:|");
+            for e in entries {
+                let _ = writeln!(
+                    md,
+                    "| {} | {} | {} | {} ({:.1}%) |",
+                    md_cell(&e.original_path),
+                    human_bytes(e.original_size as i64),
+                    human_bytes(e.optimized_size as i64),
+                    human_bytes(e.saved_bytes),
+                    e.compression_ratio
+                );
+            }
+        }
+
+        if !self.failures.is_empty() {
+            let _ = writeln!(md, "\n## Failures\n");
+            let _ = writeln!(md, "| File | Reason | Detail |\n|---|---|---|");
+            for f in &self.failures {
+                let detail = f.detail.as_deref().unwrap_or("");
+                let _ = writeln!(
+                    md,
+                    "| {} | {} | {} |",
+                    md_cell(&f.original_path),
+                    md_cell(&f.error),
+                    md_cell(detail)
+                );
+            }
+        }
+
+        let timing = &self.timing;
+        let _ = writeln!(md, "\n## Timing\n");
+        let _ = writeln!(md, "| Phase | Time |\n|---|---:|");
+        let _ = writeln!(md, "| Decode | {:.2} s |", timing.decode_ms / 1000.0);
+        let _ = writeln!(md, "| Resize | {:.2} s |", timing.resize_ms / 1000.0);
+        let _ = writeln!(md, "| Encode | {:.2} s |", timing.encode_ms / 1000.0);
+        let _ = writeln!(md, "| Write | {:.2} s |", timing.write_ms / 1000.0);
+        let _ = writeln!(md, "| Total | {:.2} s |", timing.total_ms / 1000.0);
+        let _ = writeln!(md, "| Average per image | {:.0} ms |", timing.average_ms);
+        md
+    }
+}
+
+impl From<&OptimizationResult> for ReportEntry {
+    fn from(r: &OptimizationResult) -> Self {
+        Self {
+            original_path: r.original_path.clone(),
+            optimized_path: r.optimized_path.clone(),
+            original_size: r.original_size,
+            optimized_size: r.optimized_size,
+            saved_bytes: r.saved_bytes,
+            compression_ratio: r.compression_ratio,
+        }
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────────────
+
+/// Output format of a result, falling back to its output file's extension.
+fn output_format(result: &OptimizationResult) -> String {
+    if let Some(format) = &result.details.output_format {
+        return format.clone();
+    }
+    Path::new(&result.optimized_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(normalize_format)
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn ratio(original: u64, optimized: u64) -> f64 {
+    if original > 0 {
+        (original as f64 - optimized as f64) / original as f64 * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// The status as serialized in JSON (e.g. `copiedOriginal`).
+fn status_name(status: OptimizationStatus) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// The error code as serialized in JSON (e.g. `decode_failed`).
+fn code_name(code: ErrorCode) -> String {
+    serde_json::to_value(code)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Formats an optional number, leaving the field empty when absent.
+fn optional(value: Option<u32>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Quotes a CSV field when it contains a separator, quote, or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escapes pipes and line breaks so a value stays in one table cell.
+fn md_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+/// Formats a byte count with a binary unit, keeping the sign.
+fn human_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes.unsigned_abs() as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    let sign = if bytes < 0 { "-" } else { "" };
+    if unit == 0 {
+        format!("{sign}{value:.0} {}", UNITS[unit])
+    } else {
+        format!("{sign}{value:.1} {}", UNITS[unit])
+    }
+}
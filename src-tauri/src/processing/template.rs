@@ -0,0 +1,140 @@
+// src-tauri/src/processing/template.rs
+
+//! Output path templates resolved in the backend.
+//!
+//! Tasks that arrive without an `output_path` get one rendered from
+//! `outputTemplate` (or [`DEFAULT_OUTPUT_TEMPLATE`]). Supported tokens:
+//!
+//! | Token                 | Value                                                       |
+//! |-----------------------|-------------------------------------------------------------|
+//! | `{dir}`               | Directory of the source file                                |
+//! | `{reldir}`            | Source directory relative to `sourceRoot` (empty when unset) |
+//! | `{name}`              | Source file name including its extension                    |
+//! | `{stem}`              | Source file name without extension                          |
+//! | `{ext}`               | Output extension (`jpg`, `png`, `webp`, `avif`, `svg`)      |
+//! | `{format}`            | Output format name (`jpeg`, `png`, ...)                     |
+//! | `{width}`, `{height}` | Output dimensions after resize (empty for SVG)              |
+//! | `{quality}`           | Effective quality for the output format                     |
+//! | `{date}`              | Local date as `YYYY-MM-DD`                                  |
+//! | `{hash}`              | First 8 hex digits of the source's SHA-256                  |
+//!
+//! Relative results are resolved against the source directory. A template
+//! such as `/srv/web/{reldir}/{name}` combined with a `sourceRoot` mirrors
+//! the source tree into a separate output root.
+//!
+//! With `outputFormat: "auto"`, `{ext}`, `{format}`, and `{quality}` describe
+//! the first candidate the image is eligible for. The executor replaces the
+//! file extension once encoding has picked the format; other uses keep it.
+
+use std::path::{Path, PathBuf};
+
+use crate::core::ImageTask;
+use crate::utils::error::ValidationError;
+use crate::utils::{ImageFormat, OptimizerResult, detect_format, file_sha256};
+
+use super::libvips::{effective_quality, output_dimensions, target_format};
+
+/// Layout used when no template is configured, matching `<parent>/optimized/<name>`.
+pub const DEFAULT_OUTPUT_TEMPLATE: &str = "{dir}/optimized/{name}";
+
+/// Fills in `output_path` for every task that arrived without one.
+///
+/// In-place tasks are left alone since they always target their input.
+pub fn resolve_output_paths(tasks: &mut [ImageTask]) -> OptimizerResult<()> {
+    for task in tasks
+        .iter_mut()
+        .filter(|t| t.output_path.is_empty() && !t.settings.in_place)
+    {
+        task.output_path = render_output_path(task)?;
+    }
+    Ok(())
+}
+
+/// Expands the task's output template into a concrete path.
+pub fn render_output_path(task: &ImageTask) -> OptimizerResult<String> {
+    let settings = &task.settings;
+    let template = settings
+        .output_template
+        .as_deref()
+        .filter(|t| !t.is_empty())
+        .unwrap_or(DEFAULT_OUTPUT_TEMPLATE);
+
+    let input = Path::new(&task.input_path);
+    let dir = input.parent().unwrap_or(Path::new(""));
+    let is_svg = detect_format(&task.input_path)?.format == ImageFormat::SVG;
+    let format = if is_svg {
+        "svg".to_string()
+    } else {
+        target_format(&task.input_path, settings)?
+    };
+
+    // Probed lazily so templates without size tokens never open the image.
+    let mut dimensions: Option<(i32, i32)> = None;
+    let mut dims = || -> OptimizerResult<(i32, i32)> {
+        if let Some(d) = dimensions {
+            return Ok(d);
+        }
+        let d = output_dimensions(&task.input_path, &settings.resize)?;
+        dimensions = Some(d);
+        Ok(d)
+    };
+
+    let mut rendered = String::with_capacity(template.len() + task.input_path.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find('}').ok_or_else(|| {
+            ValidationError::settings(format!("Unclosed token in output template: {template}"))
+        })?;
+
+        let value = match &after[..end] {
+            "dir" => dir.to_string_lossy().to_string(),
+            "reldir" => settings
+                .source_root
+                .as_deref()
+                .and_then(|root| dir.strip_prefix(root).ok())
+                .map(|rel| rel.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            "name" => lossy(input.file_name()),
+            "stem" => lossy(input.file_stem()),
+            "ext" => match format.as_str() {
+                "jpeg" => "jpg".to_string(),
+                other => other.to_string(),
+            },
+            "format" => format.clone(),
+            "width" if is_svg => String::new(),
+            "height" if is_svg => String::new(),
+            "width" => dims()?.0.to_string(),
+            "height" => dims()?.1.to_string(),
+            "quality" if is_svg => settings.quality.global.to_string(),
+            "quality" => effective_quality(&settings.quality, &format).to_string(),
+            "date" => chrono::Local::now().format("%Y-%m-%d").to_string(),
+            "hash" => {
+                let digest = file_sha256(input).map_err(|e| {
+                    ValidationError::settings(format!("Cannot hash '{}': {e}", task.input_path))
+                })?;
+                digest[..8].to_string()
+            }
+            unknown => {
+                return Err(ValidationError::settings(format!(
+                    "Unknown output template token: {{{unknown}}}"
+                ))
+                .into());
+            }
+        };
+
+        rendered.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    rendered.push_str(rest);
+
+    // Joining resolves relative templates against the source directory;
+    // collecting components drops the empty segments an empty `{reldir}` leaves.
+    let path: PathBuf = dir.join(rendered).components().collect();
+    Ok(path.to_string_lossy().to_string())
+}
+
+fn lossy(part: Option<&std::ffi::OsStr>) -> String {
+    part.map(|p| p.to_string_lossy().to_string()).unwrap_or_default()
+}
@@ -0,0 +1,280 @@
+// src-tauri/src/processing/watch.rs
+
+//! Watch-folder mode: optimizes images as they appear in monitored directories.
+//!
+//! A `notify` watcher forwards raw file-system events to an async loop that
+//! debounces them. A file is only processed once it has gone `debounceMs`
+//! without changing size, so half-written exports are never picked up. Ready
+//! files become [`ImageTask`]s for the regular [`NativeExecutor`], which
+//! emits the usual progress events.
+//!
+//! The watch never feeds on its own outputs: the [`PathFilter`] prunes the
+//! output folder, and every file the watch writes is remembered by size and
+//! modification time so the event caused by that write (e.g. in in-place
+//! mode) is ignored.
+
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tauri::async_runtime::JoinHandle;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tracing::{debug, info, warn};
+
+use crate::core::{ImageTask, JobQueue, OptimizationStatus, PauseGate, WatchConfig};
+use crate::processing::libvips::NativeExecutor;
+use crate::processing::progress::RunClock;
+use crate::processing::template::resolve_output_paths;
+use crate::utils::error::ValidationError;
+use crate::utils::walk::PathFilter;
+use crate::utils::{OptimizerError, OptimizerResult, extract_filename, validate_task};
+
+/// How often pending files are re-checked for readiness.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a written output is remembered to suppress its own events.
+const WRITTEN_TTL: Duration = Duration::from_secs(300);
+
+/// A running folder watch.
+///
+/// Dropping it stops OS notifications and aborts the processing loop.
+pub struct FolderWatch {
+    config: WatchConfig,
+    _watcher: RecommendedWatcher,
+    task: JoinHandle<()>,
+}
+
+impl FolderWatch {
+    /// Starts monitoring `config.path` and spawns the debounce loop.
+    pub fn start(config: WatchConfig, executor: NativeExecutor) -> OptimizerResult<Self> {
+        let root = Path::new(&config.path)
+            .canonicalize()
+            .map_err(|_| ValidationError::path_not_found(&config.path))?;
+        if !root.is_dir() {
+            return Err(ValidationError::not_a_directory(&root).into());
+        }
+        let filter = PathFilter::new(&root, &config.options)?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+            Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Folder watch error: {e}"),
+        })
+        .map_err(|e| OptimizerError::processing(format!("Cannot create folder watcher: {e}")))?;
+
+        let mode = if config.options.max_depth == Some(0) {
+            RecursiveMode::NonRecursive
+        } else {
+            RecursiveMode::Recursive
+        };
+        watcher
+            .watch(&root, mode)
+            .map_err(|e| {
+                OptimizerError::processing(format!("Cannot watch '{}'", config.path))
+                    .with_detail(e.to_string())
+                    .with_path(&config.path)
+                    .with_operation("watch")
+            })?;
+
+        let task = tauri::async_runtime::spawn(run_watch(rx, filter, config.clone(), executor));
+        info!("Watching {} for new images", root.display());
+
+        Ok(Self { config, _watcher: watcher, task })
+    }
+
+    /// Configuration this watch was started with.
+    pub fn config(&self) -> &WatchConfig {
+        &self.config
+    }
+}
+
+impl Drop for FolderWatch {
+    fn drop(&mut self) {
+        self.task.abort();
+        info!("Stopped watching {}", self.config.path);
+    }
+}
+
+/// A file seen by the watcher that has not settled yet.
+struct Pending {
+    last_change: Instant,
+    size: Option<u64>,
+}
+
+/// Size and modification time of a file the watch wrote itself.
+struct WrittenStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+    recorded: Instant,
+}
+
+impl WrittenStamp {
+    fn of(meta: &Metadata) -> Self {
+        Self {
+            len: meta.len(),
+            modified: meta.modified().ok(),
+            recorded: Instant::now(),
+        }
+    }
+
+    fn matches(&self, meta: &Metadata) -> bool {
+        self.len == meta.len() && self.modified == meta.modified().ok()
+    }
+}
+
+/// Debounce loop: collects events, waits for files to settle, and processes them.
+async fn run_watch(
+    mut rx: UnboundedReceiver<PathBuf>,
+    filter: PathFilter,
+    config: WatchConfig,
+    executor: NativeExecutor,
+) {
+    let debounce = Duration::from_millis(config.debounce_ms);
+    let mut pending: HashMap<PathBuf, Pending> = HashMap::new();
+    let mut written: HashMap<PathBuf, WrittenStamp> = HashMap::new();
+    let mut last_check = Instant::now();
+
+    loop {
+        match tokio::time::timeout(POLL_INTERVAL, rx.recv()).await {
+            Ok(Some(path)) => {
+                if filter.accepts(&path) {
+                    pending.insert(path, Pending { last_change: Instant::now(), size: None });
+                }
+            }
+            // The watcher was dropped; nothing more will arrive.
+            Ok(None) => break,
+            Err(_) => {}
+        }
+
+        // A steady stream of events must not starve the readiness check.
+        if last_check.elapsed() < POLL_INTERVAL {
+            continue;
+        }
+        last_check = Instant::now();
+
+        written.retain(|_, stamp| stamp.recorded.elapsed() < WRITTEN_TTL);
+        let ready = take_ready(&mut pending, &written, debounce);
+        if !ready.is_empty() {
+            process_ready(&ready, &filter, &config, &executor, &mut written).await;
+        }
+    }
+}
+
+/// Removes and returns every pending file whose size has been stable for `debounce`.
+fn take_ready(
+    pending: &mut HashMap<PathBuf, Pending>,
+    written: &HashMap<PathBuf, WrittenStamp>,
+    debounce: Duration,
+) -> Vec<PathBuf> {
+    let now = Instant::now();
+    let mut ready = Vec::new();
+
+    pending.retain(|path, entry| {
+        // Vanished files (temp renames, deletions) are simply forgotten.
+        let Ok(meta) = std::fs::metadata(path) else {
+            return false;
+        };
+        if entry.size != Some(meta.len()) {
+            entry.size = Some(meta.len());
+            entry.last_change = now;
+            return true;
+        }
+        if now.duration_since(entry.last_change) < debounce {
+            return true;
+        }
+        if written.get(path).is_some_and(|stamp| stamp.matches(&meta)) {
+            debug!("Ignoring own output {}", extract_filename(&path.to_string_lossy()));
+            return false;
+        }
+        ready.push(path.clone());
+        false
+    });
+
+    ready
+}
+
+/// Runs settled files through the executor and remembers what was written.
+async fn process_ready(
+    paths: &[PathBuf],
+    filter: &PathFilter,
+    config: &WatchConfig,
+    executor: &NativeExecutor,
+    written: &mut HashMap<PathBuf, WrittenStamp>,
+) {
+    let tasks: Vec<ImageTask> = paths
+        .iter()
+        .map(|p| ImageTask::from_directory_entry(filter.root(), p, &config.settings, &config.options))
+        .collect();
+
+    let tasks = match prepare_tasks(tasks).await {
+        Ok(tasks) if !tasks.is_empty() => tasks,
+        Ok(_) => return,
+        Err(e) => {
+            warn!("Watch batch for {} rejected: {e}", config.path);
+            return;
+        }
+    };
+
+    debug!("Watch picked up {} images in {}", tasks.len(), config.path);
+    // Watch batches are short-lived and never offered for resume.
+    let queue = match JobQueue::create(None, tasks) {
+        Ok(queue) => queue,
+        Err(e) => {
+            warn!("Watch batch for {} failed: {e}", config.path);
+            return;
+        }
+    };
+    let clock = RunClock::start(0);
+    let pause = PauseGate::default();
+    let run = match executor.preflight(&queue, &pause, &clock).await {
+        Ok(plan) => executor.execute_batch(&queue, &pause, &plan, usize::MAX, &clock).await,
+        Err(e) => Err(e),
+    };
+    let results = match run {
+        Ok(results) => {
+            executor.emit_job_finished(&queue, &clock);
+            results
+        }
+        Err(e) => {
+            warn!("Watch batch for {} failed: {e}", config.path);
+            return;
+        }
+    };
+
+    for result in results {
+        if result.status == OptimizationStatus::Failed {
+            continue;
+        }
+        if let Ok(meta) = std::fs::metadata(&result.optimized_path) {
+            written.insert(PathBuf::from(&result.optimized_path), WrittenStamp::of(&meta));
+        }
+    }
+}
+
+/// Renders output paths and validates each task, dropping invalid ones.
+///
+/// Unlike a user-initiated batch, one bad file must not stop the watch.
+async fn prepare_tasks(tasks: Vec<ImageTask>) -> OptimizerResult<Vec<ImageTask>> {
+    let tasks = tokio::task::spawn_blocking(move || {
+        let mut tasks = tasks;
+        resolve_output_paths(&mut tasks).map(|_| tasks)
+    })
+    .await
+    .map_err(|e| OptimizerError::processing(format!("Output path resolution panicked: {e}")))??;
+
+    let mut valid = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        match validate_task(&task).await {
+            Ok(()) => valid.push(task),
+            Err(e) => warn!("Skipping watched file {}: {e}", task.input_path),
+        }
+    }
+    Ok(valid)
+}
@@ -0,0 +1,203 @@
+// src-tauri/src/utils/container.rs
+
+//! Container-level completeness checks for detecting truncated files.
+//!
+//! libvips opens images lazily and, by default, pads a truncated JPEG or PNG
+//! with grey instead of failing, so a cut-off download would be "optimized"
+//! into a damaged output. These checks only look at the container framing
+//! (end markers and declared sizes), never at pixel data.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::utils::ImageFormat;
+
+/// How far from the end of a PNG to look for the `IEND` chunk.
+const PNG_TAIL_LEN: u64 = 1024;
+
+/// Returns `true` when the file at `path` is shorter than its container says.
+///
+/// SVG and unrecognised framing are never reported as truncated; parsing
+/// catches those.
+pub fn is_truncated(path: impl AsRef<Path>, format: ImageFormat) -> io::Result<bool> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    match format {
+        ImageFormat::JPEG => jpeg_truncated(&mut file),
+        ImageFormat::PNG => Ok(!tail_contains(&mut file, len, PNG_TAIL_LEN, b"IEND")?),
+        ImageFormat::WebP => riff_truncated(&mut file, len),
+        ImageFormat::AVIF => bmff_truncated(&mut file, len),
+        ImageFormat::SVG => Ok(false),
+    }
+}
+
+/// Whether `needle` occurs within the last `window` bytes of the file.
+fn tail_contains(file: &mut File, len: u64, window: u64, needle: &[u8]) -> io::Result<bool> {
+    let start = len.saturating_sub(window);
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::with_capacity((len - start) as usize);
+    file.read_to_end(&mut tail)?;
+    Ok(tail.windows(needle.len()).any(|w| w == needle))
+}
+
+/// A JPEG that ends before its end-of-image marker.
+///
+/// The marker segments are walked from the start and the entropy-coded data
+/// after each SOS is scanned for the next marker, so whatever follows EOI
+/// (a motion photo's video, MPF images, maker trailers) is never examined.
+fn jpeg_truncated(file: &mut File) -> io::Result<bool> {
+    let mut reader = BufReader::new(file);
+    reader.seek(SeekFrom::Start(0))?;
+    let mut soi = [0u8; 2];
+    reader.read_exact(&mut soi)?;
+    if soi != [0xFF, 0xD8] {
+        // Not JPEG framing; leave the verdict to the decoder
+        return Ok(false);
+    }
+
+    loop {
+        let Some(marker) = next_jpeg_marker(&mut reader)? else {
+            return Ok(true);
+        };
+        match marker {
+            0xD9 => return Ok(false),
+            // Markers without a length field
+            0x01 | 0xD8 => {}
+            _ => {
+                let mut length = [0u8; 2];
+                reader.read_exact(&mut length)?;
+                let length = u16::from_be_bytes(length);
+                if length < 2 {
+                    return Ok(false);
+                }
+                reader.seek_relative(i64::from(length - 2))?;
+            }
+        }
+    }
+}
+
+/// Reads up to and including the next JPEG marker and returns its code, or
+/// `None` at the end of the file.
+///
+/// Stuffed `FF 00` bytes, restart markers, and fill bytes inside
+/// entropy-coded data are passed over.
+fn next_jpeg_marker(reader: &mut impl BufRead) -> io::Result<Option<u8>> {
+    let mut after_ff = false;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        let mut found = None;
+        let mut consumed = buf.len();
+        for (i, &byte) in buf.iter().enumerate() {
+            if !after_ff {
+                after_ff = byte == 0xFF;
+                continue;
+            }
+            match byte {
+                0xFF => {}
+                0x00 | 0xD0..=0xD7 => after_ff = false,
+                marker => {
+                    found = Some(marker);
+                    consumed = i + 1;
+                    break;
+                }
+            }
+        }
+        reader.consume(consumed);
+        if found.is_some() {
+            return Ok(found);
+        }
+    }
+}
+
+/// A RIFF file whose declared payload runs past the end of the file.
+fn riff_truncated(file: &mut File, len: u64) -> io::Result<bool> {
+    let mut header = [0u8; 8];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+    let declared = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+    Ok(declared + 8 > len)
+}
+
+/// An ISO-BMFF file whose top-level boxes run past the end of the file.
+///
+/// Box sizes are untrusted, so they are compared with the bytes left rather
+/// than added to the offset first, which a huge `largesize` could wrap.
+fn bmff_truncated(file: &mut File, len: u64) -> io::Result<bool> {
+    let mut offset = 0u64;
+    while len - offset >= 8 {
+        let mut header = [0u8; 8];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut header)?;
+        let size = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+            // The box extends to the end of the file
+            0 => return Ok(false),
+            // A 64-bit size follows the box type
+            1 => {
+                if len - offset < 16 {
+                    return Ok(true);
+                }
+                let mut large = [0u8; 8];
+                file.read_exact(&mut large)?;
+                u64::from_be_bytes(large)
+            }
+            size => size as u64,
+        };
+        if size < 8 {
+            // Malformed framing; leave the verdict to the decoder
+            return Ok(false);
+        }
+        if size > len - offset {
+            return Ok(true);
+        }
+        offset += size;
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Framing of a minimal baseline JPEG: SOI, APP0, SOS, scan data, EOI.
+    fn jpeg_framing() -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8];
+        bytes.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x06, b'J', b'F', b'I', b'F']);
+        bytes.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x08, 0x01, 0x01, 0x00, 0x00, 0x3F, 0x00]);
+        // Entropy-coded data with a stuffed byte and a restart marker
+        bytes.extend_from_slice(&[0x12, 0xFF, 0x00, 0x34, 0xFF, 0xD0, 0x56]);
+        bytes.extend_from_slice(&[0xFF, 0xD9]);
+        bytes
+    }
+
+    fn check(name: &str, bytes: &[u8]) -> bool {
+        let file_name = format!("container-{}-{name}.jpg", std::process::id());
+        let path = std::env::temp_dir().join(file_name);
+        std::fs::write(&path, bytes).unwrap();
+        let truncated = is_truncated(&path, ImageFormat::JPEG);
+        std::fs::remove_file(&path).unwrap();
+        truncated.unwrap()
+    }
+
+    #[test]
+    fn jpeg_with_eoi_is_complete() {
+        assert!(!check("complete", &jpeg_framing()));
+    }
+
+    #[test]
+    fn jpeg_with_large_trailer_is_complete() {
+        // A motion photo appends its video after EOI
+        let mut bytes = jpeg_framing();
+        bytes.extend((0..100 * 1024).map(|i| (i % 251) as u8 & 0xFE));
+        assert!(!check("trailer", &bytes));
+    }
+
+    #[test]
+    fn jpeg_cut_in_scan_data_is_truncated() {
+        let bytes = jpeg_framing();
+        assert!(check("cut", &bytes[..bytes.len() - 4]));
+    }
+}
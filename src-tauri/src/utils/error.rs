@@ -1,14 +1,127 @@
 //! Error types for the image optimizer.
 //!
 //! Provides a hierarchy of error types using `thiserror` for ergonomic error handling.
+//!
+//! Every error carries a stable [`ErrorCode`] and optional [`ErrorContext`]
+//! (path, operation, format). Errors serialize to the frontend as
+//! `{ code, message, detail, path, operation, format }`, where `message` is
+//! our own wording and `detail` holds the raw text from libvips, oxvg, or the
+//! OS, so the UI can localize by `code` and still show diagnostics.
 
+use std::fmt;
 use std::io;
 use std::path::PathBuf;
 use thiserror::Error;
-use serde::Serialize;
+use serde::{Deserialize, Serialize, Serializer};
+
+/// Stable, machine-readable error codes.
+///
+/// Serialized in `snake_case`. Codes are part of the frontend contract: add
+/// new ones freely, but never rename or reuse an existing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// A file or directory does not exist
+    NotFound,
+    /// A path that should be a file is not
+    NotAFile,
+    /// A path that should be a directory is not
+    NotADirectory,
+    /// The output exists and the collision policy forbids replacing it
+    AlreadyExists,
+    /// Several tasks in one batch write the same output
+    DuplicateTarget,
+    /// Settings or command arguments are invalid
+    InvalidSettings,
+    /// The input or requested output format is not supported
+    UnsupportedFormat,
+    /// The image has transparency the output format cannot store
+    AlphaUnsupported,
+    /// The image could not be opened or decoded
+    DecodeFailed,
+    /// The file ends before its container says it should
+    Truncated,
+    /// The image exceeds a configured resource limit
+    TooLarge,
+    /// Resizing failed
+    ResizeFailed,
+    /// Encoding the output failed
+    EncodeFailed,
+    /// The SVG could not be parsed
+    SvgParseFailed,
+    /// The SVG parsed but could not be optimized
+    SvgOptimizeFailed,
+    /// The OS denied access to a path
+    PermissionDenied,
+    /// The disk or quota is full
+    DiskFull,
+    /// Any other file-system or IO failure
+    Io,
+    /// Any other processing failure
+    ProcessingFailed,
+}
+
+impl From<io::ErrorKind> for ErrorCode {
+    fn from(kind: io::ErrorKind) -> Self {
+        match kind {
+            io::ErrorKind::NotFound => Self::NotFound,
+            io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+                Self::PermissionDenied
+            }
+            io::ErrorKind::AlreadyExists => Self::AlreadyExists,
+            io::ErrorKind::NotADirectory => Self::NotADirectory,
+            io::ErrorKind::IsADirectory => Self::NotAFile,
+            io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => Self::DiskFull,
+            _ => Self::Io,
+        }
+    }
+}
+
+/// Where an error happened.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorContext {
+    /// File the failing operation was working on
+    pub path: Option<String>,
+    /// What was being done, e.g. `load`, `resize`, `encode`, `write`
+    pub operation: Option<String>,
+    /// Image format involved, e.g. `webp`
+    pub format: Option<String>,
+}
+
+/// A coded failure with its context and the underlying library message.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub code: ErrorCode,
+    /// Our own description of what failed
+    pub message: String,
+    /// Raw text from libvips, oxvg, or the OS
+    pub detail: Option<String>,
+    pub context: ErrorContext,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{}: {detail}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Serialized form of every error sent to the frontend.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorPayload {
+    code: ErrorCode,
+    message: String,
+    detail: Option<String>,
+    #[serde(flatten)]
+    context: ErrorContext,
+}
 
 /// Validation errors for input tasks and settings.
-#[derive(Error, Debug, Serialize)]
+#[derive(Error, Debug)]
 pub enum ValidationError {
     /// Path-related validation error
     #[error("Path error: {0}")]
@@ -27,6 +140,15 @@ pub enum PathError {
     /// Path exists but is not a file
     #[error("Not a file: {0}")]
     NotFile(PathBuf),
+    /// Path exists but is not a directory
+    #[error("Not a directory: {0}")]
+    NotDirectory(PathBuf),
+    /// Output path is already taken and the collision policy forbids replacing it
+    #[error("Output already exists: {0}")]
+    AlreadyExists(PathBuf),
+    /// Several tasks in one batch resolve to the same output path
+    #[error("Multiple tasks write to: {0}")]
+    DuplicateTarget(PathBuf),
     /// IO error accessing the path
     #[error("IO error: {0}")]
     IO(String),
@@ -36,7 +158,7 @@ pub enum PathError {
 ///
 /// All errors in the application are converted to this type before being
 /// returned to the frontend.
-#[derive(Error, Debug, Serialize)]
+#[derive(Error, Debug)]
 pub enum OptimizerError {
     /// Task or input validation failed
     #[error("Validation error: {0}")]
@@ -44,15 +166,19 @@ pub enum OptimizerError {
 
     /// Image processing failed
     #[error("Processing error: {0}")]
-    Processing(String),
+    Processing(Failure),
 
     /// File IO error
     #[error("IO error: {0}")]
-    IO(String),
+    IO(Failure),
 
     /// Unsupported or invalid image format
     #[error("Format error: {0}")]
-    Format(String),
+    Format(Failure),
+
+    /// An image exceeds a configured resource limit
+    #[error("Limit exceeded: {0}")]
+    LimitExceeded(Failure),
 
 }
 
@@ -61,14 +187,107 @@ pub type OptimizerResult<T> = Result<T, OptimizerError>;
 
 // Helper methods for error creation
 impl OptimizerError {
-    // Note: validation and io methods were removed as they were unused
+    /// Creates an error with `code`; the variant follows from the code.
+    pub fn new<T: Into<String>>(code: ErrorCode, msg: T) -> Self {
+        let failure = Failure {
+            code,
+            message: msg.into(),
+            detail: None,
+            context: ErrorContext::default(),
+        };
+        match code {
+            ErrorCode::UnsupportedFormat | ErrorCode::AlphaUnsupported => Self::Format(failure),
+            ErrorCode::TooLarge => Self::LimitExceeded(failure),
+            ErrorCode::NotFound
+            | ErrorCode::PermissionDenied
+            | ErrorCode::DiskFull
+            | ErrorCode::Io => Self::IO(failure),
+            _ => Self::Processing(failure),
+        }
+    }
 
     pub fn processing<T: Into<String>>(msg: T) -> Self {
-        Self::Processing(msg.into())
+        Self::new(ErrorCode::ProcessingFailed, msg)
     }
 
     pub fn format<T: Into<String>>(msg: T) -> Self {
-        Self::Format(msg.into())
+        Self::new(ErrorCode::UnsupportedFormat, msg)
+    }
+
+    /// Wraps an IO error, coding it by its kind and keeping its text as detail.
+    pub fn io<T: Into<String>>(msg: T, err: &io::Error) -> Self {
+        Self::new(err.kind().into(), msg).with_detail(err.to_string())
+    }
+
+    /// The stable code of this error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Validation(e) => e.code(),
+            Self::Processing(f) | Self::IO(f) | Self::Format(f) | Self::LimitExceeded(f) => f.code,
+        }
+    }
+
+    /// Our own description of what failed, without the raw detail.
+    pub fn message(&self) -> String {
+        self.payload().message
+    }
+
+    /// The raw library or OS message, if any.
+    pub fn detail(&self) -> Option<String> {
+        self.payload().detail
+    }
+
+    /// Sets the raw library or OS message.
+    pub fn with_detail<T: Into<String>>(mut self, detail: T) -> Self {
+        if let Some(failure) = self.failure_mut() {
+            failure.detail = Some(detail.into());
+        }
+        self
+    }
+
+    /// Sets the path, unless an inner call already did.
+    pub fn with_path<T: Into<String>>(mut self, path: T) -> Self {
+        if let Some(failure) = self.failure_mut() {
+            failure.context.path.get_or_insert_with(|| path.into());
+        }
+        self
+    }
+
+    /// Sets the operation, unless an inner call already did.
+    pub fn with_operation<T: Into<String>>(mut self, operation: T) -> Self {
+        if let Some(failure) = self.failure_mut() {
+            failure.context.operation.get_or_insert_with(|| operation.into());
+        }
+        self
+    }
+
+    /// Sets the format, unless an inner call already did.
+    pub fn with_format<T: Into<String>>(mut self, format: T) -> Self {
+        if let Some(failure) = self.failure_mut() {
+            failure.context.format.get_or_insert_with(|| format.into());
+        }
+        self
+    }
+
+    fn failure_mut(&mut self) -> Option<&mut Failure> {
+        match self {
+            Self::Validation(_) => None,
+            Self::Processing(f) | Self::IO(f) | Self::Format(f) | Self::LimitExceeded(f) => Some(f),
+        }
+    }
+
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::Validation(e) => e.payload(),
+            Self::Processing(f) | Self::IO(f) | Self::Format(f) | Self::LimitExceeded(f) => {
+                ErrorPayload {
+                    code: f.code,
+                    message: f.message.clone(),
+                    detail: f.detail.clone(),
+                    context: f.context.clone(),
+                }
+            }
+        }
     }
 }
 
@@ -82,15 +301,80 @@ impl ValidationError {
         Self::Path(PathError::NotFile(path.into()))
     }
 
+    pub fn not_a_directory(path: impl Into<PathBuf>) -> Self {
+        Self::Path(PathError::NotDirectory(path.into()))
+    }
+
+    pub fn already_exists(path: impl Into<PathBuf>) -> Self {
+        Self::Path(PathError::AlreadyExists(path.into()))
+    }
+
+    pub fn duplicate_target(path: impl Into<PathBuf>) -> Self {
+        Self::Path(PathError::DuplicateTarget(path.into()))
+    }
+
     pub fn settings(msg: impl Into<String>) -> Self {
         Self::Settings(msg.into())
     }
+
+    /// The stable code of this error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Path(PathError::NotFound(_)) => ErrorCode::NotFound,
+            Self::Path(PathError::NotFile(_)) => ErrorCode::NotAFile,
+            Self::Path(PathError::NotDirectory(_)) => ErrorCode::NotADirectory,
+            Self::Path(PathError::AlreadyExists(_)) => ErrorCode::AlreadyExists,
+            Self::Path(PathError::DuplicateTarget(_)) => ErrorCode::DuplicateTarget,
+            Self::Path(PathError::IO(_)) => ErrorCode::Io,
+            Self::Settings(_) => ErrorCode::InvalidSettings,
+        }
+    }
+
+    /// Where the error happened; validation errors only know the path.
+    pub fn context(&self) -> ErrorContext {
+        let path = match self {
+            Self::Path(
+                PathError::NotFound(p)
+                | PathError::NotFile(p)
+                | PathError::NotDirectory(p)
+                | PathError::AlreadyExists(p)
+                | PathError::DuplicateTarget(p),
+            ) => Some(p.to_string_lossy().to_string()),
+            _ => None,
+        };
+        ErrorContext { path, operation: Some("validate".to_string()), format: None }
+    }
+
+    fn payload(&self) -> ErrorPayload {
+        let detail = match self {
+            Self::Path(PathError::IO(detail)) => Some(detail.clone()),
+            _ => None,
+        };
+        ErrorPayload {
+            code: self.code(),
+            message: self.to_string(),
+            detail,
+            context: self.context(),
+        }
+    }
+}
+
+impl Serialize for OptimizerError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.payload().serialize(serializer)
+    }
+}
+
+impl Serialize for ValidationError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.payload().serialize(serializer)
+    }
 }
 
 // Convert std::io::Error to OptimizerError
 impl From<io::Error> for OptimizerError {
     fn from(err: io::Error) -> Self {
-        Self::IO(err.to_string())
+        Self::new(err.kind().into(), err.to_string())
     }
 }
 
@@ -106,4 +390,4 @@ impl From<PathError> for OptimizerError {
     fn from(err: PathError) -> Self {
         Self::Validation(ValidationError::Path(err))
     }
-} 
\ No newline at end of file
+}
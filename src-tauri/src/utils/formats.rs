@@ -1,10 +1,20 @@
 // src-tauri/src/utils/formats.rs
 
 //! Image format detection and parsing.
+//!
+//! Input formats are detected from content first: [`detect_format`] reads
+//! the file's leading bytes and only falls back to the extension when they
+//! match no supported format. A PNG named `.jpg` or an extension-less
+//! download is therefore encoded as what it really is.
 
+use std::io::Read;
+use std::path::Path;
 use std::str::FromStr;
 use crate::utils::OptimizerError;
 
+/// Bytes read for sniffing; enough for an SVG's XML prolog and comments.
+const SNIFF_LEN: usize = 4096;
+
 /// Supported image formats for optimization.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(clippy::upper_case_acronyms)]
@@ -21,6 +31,19 @@ pub enum ImageFormat {
     SVG,
 }
 
+impl ImageFormat {
+    /// Canonical lowercase name, as used for output formats (`jpeg`, `png`, ...).
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::JPEG => "jpeg",
+            Self::PNG => "png",
+            Self::WebP => "webp",
+            Self::AVIF => "avif",
+            Self::SVG => "svg",
+        }
+    }
+}
+
 impl FromStr for ImageFormat {
     type Err = OptimizerError;
 
@@ -51,6 +74,117 @@ pub fn format_from_extension(path: &str) -> Result<ImageFormat, OptimizerError>
     ImageFormat::from_str(ext)
 }
 
+/// How an input's format was determined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatSource {
+    /// The file's leading bytes
+    Content,
+    /// The file extension, because the content matched no supported format
+    Extension,
+}
+
+/// An input's detected format and how it compares to its extension.
+#[derive(Debug, Clone)]
+pub struct DetectedFormat {
+    pub format: ImageFormat,
+    pub source: FormatSource,
+    /// Normalized extension of the path, if it has one
+    pub extension: Option<String>,
+}
+
+impl DetectedFormat {
+    /// Whether the extension names a different format than the content.
+    pub fn extension_mismatch(&self) -> bool {
+        self.extension
+            .as_deref()
+            .is_some_and(|ext| ext != self.format.name())
+    }
+}
+
+/// Detects the format of the file at `path` from its content, falling back
+/// to the extension when the content is inconclusive or unreadable.
+pub fn detect_format(path: &str) -> Result<DetectedFormat, OptimizerError> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(normalize_format);
+
+    if let Some(format) = read_header(path).as_deref().and_then(sniff_format) {
+        return Ok(DetectedFormat { format, source: FormatSource::Content, extension });
+    }
+
+    let format = format_from_extension(path)?;
+    Ok(DetectedFormat { format, source: FormatSource::Extension, extension })
+}
+
+/// Identifies a supported format from a file's leading bytes.
+pub fn sniff_format(header: &[u8]) -> Option<ImageFormat> {
+    if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(ImageFormat::JPEG);
+    }
+    if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(ImageFormat::PNG);
+    }
+    if header.len() >= 12 && &header[..4] == b"RIFF" && &header[8..12] == b"WEBP" {
+        return Some(ImageFormat::WebP);
+    }
+    if is_avif(header) {
+        return Some(ImageFormat::AVIF);
+    }
+    if is_svg(header) {
+        return Some(ImageFormat::SVG);
+    }
+    None
+}
+
+/// Maps a libvips loader name (the `vips-loader` header field, e.g.
+/// `jpegload_source`) to the format it decodes.
+///
+/// `heifload` also reads HEIC, so it only says the input is HEIF-based.
+pub fn format_from_loader(loader: &str) -> Option<ImageFormat> {
+    let base = loader.split('_').next().unwrap_or(loader);
+    match base {
+        "jpegload" => Some(ImageFormat::JPEG),
+        "pngload" => Some(ImageFormat::PNG),
+        "webpload" => Some(ImageFormat::WebP),
+        "heifload" => Some(ImageFormat::AVIF),
+        "svgload" => Some(ImageFormat::SVG),
+        _ => None,
+    }
+}
+
+fn read_header(path: &str) -> Option<Vec<u8>> {
+    let mut header = Vec::with_capacity(SNIFF_LEN);
+    std::fs::File::open(path)
+        .ok()?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut header)
+        .ok()?;
+    Some(header)
+}
+
+/// An ISO-BMFF `ftyp` box whose major or compatible brands include AVIF.
+fn is_avif(header: &[u8]) -> bool {
+    if header.len() < 16 || &header[4..8] != b"ftyp" {
+        return false;
+    }
+    let box_len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let end = box_len.clamp(16, header.len());
+    let major = &header[8..12];
+    let compatible = header[16..end].chunks_exact(4);
+    std::iter::once(major)
+        .chain(compatible)
+        .any(|brand| brand == b"avif" || brand == b"avis")
+}
+
+/// Text that opens with an XML prolog, doctype, comment, or `<svg` tag and
+/// contains an `<svg` element.
+fn is_svg(header: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(header);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    text.starts_with('<') && text.contains("<svg")
+}
+
 /// Normalizes a format string: lowercases and maps "jpg" to "jpeg".
 pub fn normalize_format(fmt: &str) -> String {
     match fmt.to_lowercase().as_str() {
@@ -58,3 +192,51 @@ pub fn normalize_format(fmt: &str) -> String {
         other => other.to_string(),
     }
 }
+
+/// Resolves "original" to the detected input format and normalizes "jpg" → "jpeg".
+pub fn resolve_output_format(input_path: &str, requested: &str) -> Result<String, OptimizerError> {
+    if requested == "original" {
+        return Ok(detect_format(input_path)?.format.name().to_string());
+    }
+
+    Ok(normalize_format(requested))
+}
+
+/// Returns `output_path` with the extension corrected to match `format`.
+///
+/// When the output format differs from the extension already on `output_path`
+/// (e.g. converting foo.jpg → webp), the extension is replaced.
+pub fn ensure_correct_extension(output_path: &str, input_path: &str, format: &str) -> String {
+    let new_ext = match format {
+        "jpeg" => "jpg",
+        other => other,
+    };
+
+    let path = Path::new(output_path);
+    let current_ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    if normalize_format(&current_ext) == format {
+        return output_path.to_string();
+    }
+
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let parent = path.parent().unwrap_or(Path::new(""));
+
+    let stem = if stem.is_empty() {
+        Path::new(input_path)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output")
+    } else {
+        stem
+    };
+
+    parent
+        .join(format!("{stem}.{new_ext}"))
+        .to_string_lossy()
+        .to_string()
+}
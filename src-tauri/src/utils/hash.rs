@@ -0,0 +1,30 @@
+// src-tauri/src/utils/hash.rs
+
+//! Content hashing for output templates and change detection.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use sha2::{Digest, Sha256};
+
+/// Returns the lowercase hex SHA-256 digest of a file's contents.
+///
+/// Reads in 64 KiB blocks so large images are never held in memory at once.
+pub fn file_sha256(path: impl AsRef<Path>) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Encodes bytes as lowercase hex.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
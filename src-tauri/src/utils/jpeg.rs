@@ -0,0 +1,92 @@
+// src-tauri/src/utils/jpeg.rs
+
+//! JPEG header inspection for detecting already-compressed files.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+/// IJG standard luminance quantization table (Annex K of the JPEG spec).
+///
+/// Only the sum is compared, so element order does not matter.
+const STD_LUMINANCE: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69,
+    56, 14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104,
+    113, 92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+
+/// Estimates the IJG quality (1–100) the file was encoded at.
+///
+/// Reads the luminance quantization table from the header and inverts the
+/// IJG scaling formula. Encoders with custom tables (e.g. mozjpeg) only get
+/// an approximation, which is enough to tell "already heavily compressed"
+/// apart from camera output. Returns `None` when no table is found before
+/// the image data starts.
+pub fn estimate_quality(path: impl AsRef<Path>) -> io::Result<Option<u32>> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut soi = [0u8; 2];
+    reader.read_exact(&mut soi)?;
+    if soi != [0xFF, 0xD8] {
+        return Ok(None);
+    }
+
+    loop {
+        let mut marker = [0u8; 2];
+        reader.read_exact(&mut marker)?;
+        if marker[0] != 0xFF {
+            return Ok(None);
+        }
+        // Start of scan or end of image: no table in the header.
+        if marker[1] == 0xDA || marker[1] == 0xD9 {
+            return Ok(None);
+        }
+
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        let len = u16::from_be_bytes(len).saturating_sub(2) as usize;
+        let mut segment = vec![0u8; len];
+        reader.read_exact(&mut segment)?;
+
+        if marker[1] == 0xDB
+            && let Some(quality) = luminance_quality(&segment)
+        {
+            return Ok(Some(quality));
+        }
+    }
+}
+
+/// Finds table 0 in a DQT segment and converts it to an IJG quality.
+fn luminance_quality(segment: &[u8]) -> Option<u32> {
+    let mut rest = segment;
+    while let Some((&info, tail)) = rest.split_first() {
+        let wide = info >> 4 == 1;
+        let size = if wide { 128 } else { 64 };
+        let table = tail.get(..size)?;
+        rest = &tail[size..];
+
+        if info & 0x0F != 0 {
+            continue;
+        }
+
+        let sum: u64 = if wide {
+            table
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]) as u64)
+                .sum()
+        } else {
+            table.iter().map(|&v| v as u64).sum()
+        };
+        let std_sum: u64 = STD_LUMINANCE.iter().map(|&v| v as u64).sum();
+
+        // IJG: scale = 5000 / q below 50, 200 - 2q from 50 up.
+        let scale = sum as f64 * 100.0 / std_sum as f64;
+        let quality = if scale <= 100.0 {
+            (200.0 - scale) / 2.0
+        } else {
+            5000.0 / scale
+        };
+        return Some(quality.round().clamp(1.0, 100.0) as u32);
+    }
+    None
+}
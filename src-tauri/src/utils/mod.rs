@@ -3,12 +3,26 @@
 //! This module provides:
 //! - [`OptimizerError`]: Unified error type for the application
 //! - [`validate_task`]: Task validation before processing
-//! - [`format_from_extension`]: Image format detection from file extensions
+//! - [`validate_batch`]: Batch-wide validation including output collisions
+//! - [`detect_format`]: Image format detection from content, falling back to the extension
+//! - [`file_sha256`]: Streaming content hash of a file
+//! - [`estimate_jpeg_quality`]: Quality estimate from a JPEG's quantization tables
+//! - [`collect_images`]: Recursive directory scan with glob filters
 
+pub mod container;
 pub mod error;
 pub mod validation;
 pub mod formats;
+pub mod hash;
+pub mod jpeg;
+pub mod walk;
 
 pub use error::{OptimizerError, OptimizerResult};
-pub use validation::{validate_task, extract_filename};
-pub use formats::{ImageFormat, format_from_extension, normalize_format}; 
\ No newline at end of file
+pub use validation::{validate_task, validate_batch, extract_filename};
+pub use hash::file_sha256;
+pub use jpeg::estimate_quality as estimate_jpeg_quality;
+pub use walk::collect_images;
+pub use formats::{
+    DetectedFormat, FormatSource, ImageFormat, detect_format, format_from_extension,
+    format_from_loader, normalize_format, resolve_output_format, ensure_correct_extension,
+}; 
\ No newline at end of file
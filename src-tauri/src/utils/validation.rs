@@ -1,8 +1,14 @@
 // src-tauri/src/utils/validation.rs
 
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::path::Path;
-use crate::core::ImageTask;
-use crate::utils::{ImageFormat, OptimizerResult, format_from_extension};
+use tracing::warn;
+use crate::core::{
+    AdvancedSettings, AutoFormatSettings, BackupMode, CollisionPolicy, Compression, ImageTask,
+    SizePolicy,
+};
+use crate::utils::{ImageFormat, OptimizerResult, detect_format};
 use crate::utils::error::ValidationError;
 use tokio::fs;
 
@@ -21,13 +27,67 @@ pub fn extract_filename(path: &str) -> &str {
 /// validate quality, resize, and output format settings.
 pub async fn validate_task(task: &ImageTask) -> OptimizerResult<()> {
     let format = validate_input_path(&task.input_path).await?;
-    validate_output_path(&task.output_path).await?;
+    validate_output_path(task.requested_output_path()).await?;
+    validate_backup_settings(&task.settings)?;
     if format != ImageFormat::SVG {
         validate_settings(&task.settings)?;
     }
     Ok(())
 }
 
+/// Validates a whole batch before any encoding starts.
+///
+/// Runs [`validate_task`] on every task, then resolves each final output
+/// path to catch collisions up front. Tasks using the `fail` collision policy
+/// reject the batch when their target already exists or is shared with
+/// another task; `overwrite` only warns, and `skip`/`rename` are applied by
+/// the executor as each task runs.
+pub async fn validate_batch(tasks: &[ImageTask]) -> OptimizerResult<()> {
+    let mut targets: HashMap<String, &str> = HashMap::with_capacity(tasks.len());
+
+    for task in tasks {
+        validate_task(task).await?;
+
+        let target = task.resolved_output_path()?;
+        if target == task.input_path {
+            continue;
+        }
+
+        let policy = task.settings.collision_policy;
+        if let Some(previous) = targets.insert(collision_key(&target), &task.input_path) {
+            match policy {
+                CollisionPolicy::Fail => {
+                    return Err(ValidationError::duplicate_target(&target).into());
+                }
+                CollisionPolicy::Overwrite => warn!(
+                    "'{}' and '{}' both write to {}; the later task overwrites",
+                    extract_filename(previous),
+                    extract_filename(&task.input_path),
+                    target
+                ),
+                CollisionPolicy::Skip | CollisionPolicy::Rename => {}
+            }
+        }
+
+        if policy == CollisionPolicy::Fail && Path::new(&target).exists() {
+            return Err(ValidationError::already_exists(&target).into());
+        }
+    }
+    Ok(())
+}
+
+/// Normalizes a path for duplicate detection.
+///
+/// Windows and macOS default to case-insensitive file systems, so
+/// `Photo.webp` and `photo.webp` name the same file there.
+fn collision_key(path: &str) -> String {
+    if cfg!(any(windows, target_os = "macos")) {
+        path.to_lowercase()
+    } else {
+        path.to_string()
+    }
+}
+
 /// Validates that an input path exists and is a supported format.
 ///
 /// Returns the detected `ImageFormat` so callers can branch on SVG vs raster.
@@ -41,8 +101,8 @@ pub async fn validate_input_path(path: impl AsRef<Path>) -> OptimizerResult<Imag
     }
     let path_str = path.to_str()
         .ok_or_else(|| ValidationError::settings("Path contains invalid characters"))?;
-    let format = format_from_extension(path_str)?;
-    Ok(format)
+    let detected = detect_format(path_str)?;
+    Ok(detected.format)
 }
 
 /// Validates that an output path has a valid parent directory.
@@ -61,6 +121,19 @@ pub async fn validate_output_path(path: impl AsRef<Path>) -> OptimizerResult<()>
     Ok(())
 }
 
+/// Validates that backup settings are complete for in-place tasks.
+pub fn validate_backup_settings(settings: &crate::core::ImageSettings) -> OptimizerResult<()> {
+    if settings.in_place
+        && settings.backup.mode == BackupMode::Directory
+        && settings.backup.directory.as_deref().is_none_or(str::is_empty)
+    {
+        return Err(ValidationError::settings(
+            "Backup directory is required when backup mode is 'directory'"
+        ).into());
+    }
+    Ok(())
+}
+
 /// Validates raster image settings for quality and resize parameters.
 ///
 /// Not called for SVG tasks since quality, resize, and format conversion
@@ -73,11 +146,47 @@ pub fn validate_settings(settings: &crate::core::ImageSettings) -> OptimizerResu
     }
 
     let format = settings.output_format.to_lowercase();
-    if !["jpeg", "jpg", "png", "webp", "avif", "original"].contains(&format.as_str()) {
+    if !["jpeg", "jpg", "png", "webp", "avif", "original", "auto"].contains(&format.as_str()) {
         return Err(ValidationError::settings(
             format!("Unsupported output format: {}", format)
         ).into());
     }
+    if format == "auto" {
+        validate_auto(&settings.auto)?;
+    }
+
+    if settings.size_policy == SizePolicy::MinSavings {
+        match settings.min_savings_percent {
+            Some(pct) if (0.0..=100.0).contains(&pct) => {}
+            Some(pct) => {
+                return Err(ValidationError::settings(
+                    format!("Invalid minimum savings: {}. Must be between 0 and 100", pct)
+                ).into());
+            }
+            None => {
+                return Err(ValidationError::settings(
+                    "minSavingsPercent is required for the minSavings size policy"
+                ).into());
+            }
+        }
+    }
+
+    let modes = &settings.quality.mode;
+    if modes.jpeg.is_some_and(|mode| mode != Compression::Lossy) {
+        return Err(ValidationError::settings("JPEG has no lossless or near-lossless mode").into());
+    }
+    if modes.png == Some(Compression::NearLossless) {
+        return Err(ValidationError::settings("PNG has no near-lossless mode").into());
+    }
+    if settings.advanced.webp.near_lossless == Some(true)
+        && modes.webp.is_some_and(|mode| mode != Compression::NearLossless)
+    {
+        return Err(ValidationError::settings(
+            "WebP nearLossless conflicts with quality.mode.webp; set only the mode"
+        ).into());
+    }
+
+    validate_advanced(&settings.advanced)?;
 
     if let Some(width) = settings.resize.width
         && width == 0
@@ -91,3 +200,68 @@ pub fn validate_settings(settings: &crate::core::ImageSettings) -> OptimizerResu
     }
     Ok(())
 }
+
+/// Validates the candidate list and threshold of `outputFormat: "auto"`.
+fn validate_auto(auto: &AutoFormatSettings) -> OptimizerResult<()> {
+    if auto.candidates.is_empty() {
+        return Err(ValidationError::settings("Auto format needs at least one candidate").into());
+    }
+    for candidate in &auto.candidates {
+        if !["jpeg", "jpg", "png", "webp", "avif"].contains(&candidate.to_lowercase().as_str()) {
+            return Err(ValidationError::settings(
+                format!("Unsupported auto candidate: {}", candidate)
+            ).into());
+        }
+    }
+    if let Some(max) = auto.max_delta_e
+        && !(0.0..=100.0).contains(&max)
+    {
+        return Err(ValidationError::settings(
+            format!("Invalid maximum ΔE: {}. Must be between 0 and 100", max)
+        ).into());
+    }
+    Ok(())
+}
+
+/// Validates the ranges of the per-format encoder options.
+fn validate_advanced(advanced: &AdvancedSettings) -> OptimizerResult<()> {
+    let AdvancedSettings { jpeg, png, webp, avif } = advanced;
+    check_range("JPEG quant table", jpeg.quant_table, 0..=8)?;
+    check_range("PNG colors", png.colors, 2..=256)?;
+    check_range("WebP effort", webp.effort, 0..=6)?;
+    check_range("WebP alpha quality", webp.alpha_quality, 0..=100)?;
+    check_range("AVIF effort", avif.effort, 0..=9)?;
+
+    if png.colors.is_some() && png.palette == Some(false) {
+        return Err(ValidationError::settings("PNG colors requires palette output").into());
+    }
+    if let Some(dither) = png.dither
+        && !(0.0..=1.0).contains(&dither)
+    {
+        return Err(ValidationError::settings(
+            format!("Invalid PNG dither: {}. Must be between 0 and 1", dither)
+        ).into());
+    }
+    if let Some(depth) = avif.bit_depth
+        && ![8, 10, 12].contains(&depth)
+    {
+        return Err(ValidationError::settings(
+            format!("Invalid AVIF bit depth: {}. Must be 8, 10, or 12", depth)
+        ).into());
+    }
+    Ok(())
+}
+
+fn check_range(name: &str, value: Option<u32>, range: RangeInclusive<u32>) -> OptimizerResult<()> {
+    match value {
+        Some(v) if !range.contains(&v) => Err(ValidationError::settings(format!(
+            "Invalid {}: {}. Must be between {} and {}",
+            name,
+            v,
+            range.start(),
+            range.end()
+        ))
+        .into()),
+        _ => Ok(()),
+    }
+}
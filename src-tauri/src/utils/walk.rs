@@ -0,0 +1,213 @@
+// src-tauri/src/utils/walk.rs
+
+//! Recursive directory scanning for supported images.
+//!
+//! [`PathFilter`] holds the include/exclude, depth, hidden-file, and
+//! output-folder rules for one root so the same decisions apply to a full
+//! scan ([`collect_images`]) and to single paths reported by a folder watcher.
+
+use std::path::{Path, PathBuf};
+use glob::{MatchOptions, Pattern};
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+use crate::core::{DirectoryOptions, SymlinkPolicy};
+use crate::utils::error::ValidationError;
+use crate::utils::{OptimizerError, OptimizerResult, detect_format, format_from_extension};
+
+/// Folder name the default output template writes into; pruned when no
+/// explicit output root is given so earlier outputs are not re-ingested.
+const DEFAULT_OUTPUT_DIR: &str = "optimized";
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Selection rules for images below one root directory.
+pub struct PathFilter {
+    root: PathBuf,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    max_depth: Option<usize>,
+    include_hidden: bool,
+    follow_symlinks: bool,
+    has_output_root: bool,
+    output_root: Option<PathBuf>,
+}
+
+impl PathFilter {
+    /// Compiles the glob patterns in `options` for scanning `root`.
+    pub fn new(root: &Path, options: &DirectoryOptions) -> OptimizerResult<Self> {
+        Ok(Self {
+            root: root.to_path_buf(),
+            include: compile(&options.include)?,
+            exclude: compile(&options.exclude)?,
+            max_depth: options.max_depth,
+            include_hidden: options.include_hidden,
+            follow_symlinks: options.symlinks == SymlinkPolicy::Follow,
+            has_output_root: options.output_root.is_some(),
+            output_root: options
+                .output_root
+                .as_deref()
+                .and_then(|p| Path::new(p).canonicalize().ok()),
+        })
+    }
+
+    /// Root directory the rules are relative to.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Checks an arbitrary path, including every directory between it and the root.
+    ///
+    /// Used for paths reported by a watcher, which arrive without the pruning
+    /// a directory walk gets for free.
+    pub fn accepts(&self, path: &Path) -> bool {
+        let Ok(rel) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let depth = rel.components().count();
+        if depth == 0 || self.max_depth.is_some_and(|max| depth > max + 1) {
+            return false;
+        }
+
+        let mut dir = self.root.clone();
+        if let Some(parent) = rel.parent() {
+            for component in parent.components() {
+                dir.push(component);
+                if !self.allows_dir(&dir) {
+                    return false;
+                }
+            }
+        }
+
+        if !self.follow_symlinks
+            && path.symlink_metadata().is_ok_and(|m| m.file_type().is_symlink())
+        {
+            return false;
+        }
+        self.allows_file(path)
+    }
+
+    /// Whether a directory below the root may be descended into.
+    fn allows_dir(&self, path: &Path) -> bool {
+        !self.is_hidden(path) && !self.is_excluded(path) && !self.is_output_dir(path)
+    }
+
+    /// Whether a file is a supported image selected by the filters.
+    ///
+    /// Files with an extension are judged by it; extension-less files (e.g.
+    /// downloads) are sniffed.
+    fn allows_file(&self, path: &Path) -> bool {
+        !self.is_hidden(path)
+            && !self.is_excluded(path)
+            && (self.include.is_empty() || matches(&self.include, self.relative(path)))
+            && path.to_str().is_some_and(|p| match path.extension() {
+                Some(_) => format_from_extension(p).is_ok(),
+                None => detect_format(p).is_ok(),
+            })
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    fn is_hidden(&self, path: &Path) -> bool {
+        !self.include_hidden
+            && path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with('.'))
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        matches(&self.exclude, self.relative(path))
+    }
+
+    fn is_output_dir(&self, path: &Path) -> bool {
+        match (self.has_output_root, &self.output_root) {
+            (true, Some(out)) => path.canonicalize().is_ok_and(|p| &p == out),
+            (true, None) => false,
+            (false, _) => path.file_name().is_some_and(|n| n == DEFAULT_OUTPUT_DIR),
+        }
+    }
+}
+
+fn compile(patterns: &[String]) -> OptimizerResult<Vec<Pattern>> {
+    patterns
+        .iter()
+        .map(|p| {
+            Pattern::new(p).map_err(|e| {
+                OptimizerError::from(ValidationError::settings(format!(
+                    "Invalid glob pattern '{p}': {e}"
+                )))
+            })
+        })
+        .collect()
+}
+
+/// Patterns with a `/` match the relative path; others match the file name.
+fn matches(patterns: &[Pattern], rel: &Path) -> bool {
+    patterns.iter().any(|p| {
+        if p.as_str().contains('/') {
+            p.matches_path_with(rel, MATCH_OPTIONS)
+        } else {
+            rel.file_name()
+                .is_some_and(|n| p.matches_with(&n.to_string_lossy(), MATCH_OPTIONS))
+        }
+    })
+}
+
+/// Walks `root` and returns every supported image that passes `options`.
+///
+/// Excluded and hidden directories are pruned rather than filtered, so their
+/// contents are never visited. The output root (or, without one, any
+/// `optimized/` folder) is pruned as well. Unreadable entries and symlink
+/// loops are logged and skipped. Results are sorted by path within each directory.
+pub fn collect_images(root: &Path, options: &DirectoryOptions) -> OptimizerResult<Vec<PathBuf>> {
+    if !root.exists() {
+        return Err(ValidationError::path_not_found(root).into());
+    }
+    if !root.is_dir() {
+        return Err(ValidationError::not_a_directory(root).into());
+    }
+
+    let filter = PathFilter::new(root, options)?;
+
+    let mut walker = WalkDir::new(root)
+        .follow_links(filter.follow_symlinks)
+        .sort_by_file_name();
+    if let Some(depth) = options.max_depth {
+        // walkdir counts the root as depth 0 and its files as depth 1.
+        walker = walker.max_depth(depth + 1);
+    }
+
+    let entries = walker.into_iter().filter_entry(|entry| {
+        if entry.depth() == 0 {
+            return true;
+        }
+        if !filter.follow_symlinks && entry.path_is_symlink() {
+            return false;
+        }
+        !entry.file_type().is_dir() || filter.allows_dir(entry.path())
+    });
+
+    let mut images = Vec::new();
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Skipping entry while scanning {}: {e}", root.display());
+                continue;
+            }
+        };
+        if entry.file_type().is_file() && filter.allows_file(entry.path()) {
+            images.push(entry.into_path());
+        }
+    }
+
+    debug!("Found {} images under {}", images.len(), root.display());
+    Ok(images)
+}
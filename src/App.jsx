@@ -42,7 +42,7 @@ function App() {
   const { t } = useTranslation();
 
   // Use our custom hook for progress tracking
-  const { progress, initProgress, processingRef } = useProgressTracker();
+  const { progress, initProgress, setJobId, processingRef } = useProgressTracker();
 
   const [settings, setSettings] = useState({
     quality: {
@@ -252,7 +252,8 @@ function App() {
             }),
           );
 
-          return invoke("optimize_images", { tasks });
+          const jobId = await invoke("optimize_images", { tasks });
+          setJobId(jobId);
         })();
 
         const animationPromise = (async () => {
@@ -275,7 +276,7 @@ function App() {
         processingRef.current = false;
       }
     },
-    [settings, initProgress, processingRef, showToast, t],
+    [settings, initProgress, setJobId, processingRef, showToast, t],
   );
 
   // Handle click on dropzone to open file picker
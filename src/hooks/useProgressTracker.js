@@ -9,9 +9,16 @@ const TIMER_INTERVAL_MS = 100;
  *
  * The backend emits `image_optimization_progress` events with overall
  * (not per-chunk) `completedTasks` / `totalTasks` counts, so this hook
- * simply forwards those values into React state.
+ * simply forwards those values into React state. While a large image is
+ * being encoded, `image_optimization_image_progress` events move the
+ * percentage forward before the image finishes.
  *
- * @returns {{ progress: Object, initProgress: Function, processingRef: React.MutableRefObject<boolean> }}
+ * Only events of the job passed to `setJobId` are counted; other jobs
+ * (watch folders, concurrent batches) emit the same events. Events that
+ * arrive before the backend has returned the job id are held back and
+ * replayed once it is known.
+ *
+ * @returns {{ progress: Object, initProgress: Function, setJobId: Function, processingRef: React.MutableRefObject<boolean> }}
  */
 function useProgressTracker() {
   const processingRef = useRef(false);
@@ -32,6 +39,8 @@ function useProgressTracker() {
   });
 
   const timerRef = useRef(null);
+  const jobIdRef = useRef(null);
+  const pendingRef = useRef([]);
 
   // Elapsed-time ticker — runs from initProgress until the final event
   // arrives, independent of the FADE_IN / PROCESSING state machine.
@@ -55,25 +64,32 @@ function useProgressTracker() {
   // Clean up timer on unmount
   useEffect(() => stopTimer, [stopTimer]);
 
-  // Single event listener — registered once on mount
-  useEffect(() => {
-    const unsubscribe = listen("image_optimization_progress", (event) => {
-      if (!processingRef.current) return;
+  // Applies an event of our job now, or holds it until the job id is known
+  const routeEvent = useCallback((payload, apply) => {
+    if (jobIdRef.current === null) {
+      pendingRef.current.push([payload, apply]);
+    } else if (payload.jobId === jobIdRef.current) {
+      apply(payload);
+    }
+  }, []);
 
+  const applyProgress = useCallback(
+    (payload) => {
       const {
         completedTasks,
         totalTasks,
         progressPercentage,
         status,
+        elapsedSeconds,
         metadata,
-      } = event.payload;
+      } = payload;
 
       const stats = statsRef.current;
 
-      // Accumulate per-image size stats when present
-      if (metadata?.savedBytes != null && metadata?.originalSize != null) {
-        stats.totalSavedBytes += Number(metadata.savedBytes);
-        stats.totalOriginalSize += Number(metadata.originalSize);
+      // Accumulate per-image size stats (paused events carry no metadata)
+      if (metadata) {
+        stats.totalSavedBytes += metadata.savedBytes;
+        stats.totalOriginalSize += metadata.originalSize;
       }
 
       const savedPercentage =
@@ -86,8 +102,8 @@ function useProgressTracker() {
         ? (Date.now() - stats.startTime) / 1000
         : 0;
 
-      if (status === "complete" && metadata?.totalDuration) {
-        processingTime = parseFloat(metadata.totalDuration);
+      if (status === "complete") {
+        processingTime = elapsedSeconds;
         stopTimer();
       }
 
@@ -100,12 +116,42 @@ function useProgressTracker() {
         savedPercentage,
         processingTime,
       });
+    },
+    [stopTimer],
+  );
+
+  // Progress within a large image; never moves the bar backwards
+  const applyImageProgress = useCallback((payload) => {
+    const { progressPercentage } = payload;
+    setProgress((prev) =>
+      progressPercentage > prev.progressPercentage
+        ? { ...prev, progressPercentage }
+        : prev
+    );
+  }, []);
+
+  // Event listeners — registered once on mount
+  useEffect(() => {
+    const unsubscribe = listen("image_optimization_progress", (event) => {
+      if (!processingRef.current) return;
+      routeEvent(event.payload, applyProgress);
     });
 
     return () => {
       unsubscribe.then((fn) => fn());
     };
-  }, [stopTimer]);
+  }, [routeEvent, applyProgress]);
+
+  useEffect(() => {
+    const unsubscribe = listen("image_optimization_image_progress", (event) => {
+      if (!processingRef.current) return;
+      routeEvent(event.payload, applyImageProgress);
+    });
+
+    return () => {
+      unsubscribe.then((fn) => fn());
+    };
+  }, [routeEvent, applyImageProgress]);
 
   /**
    * Reset all tracking state and start the elapsed-time timer.
@@ -114,6 +160,8 @@ function useProgressTracker() {
    * @param {number} fileCount - Number of files to process
    */
   const initProgress = (fileCount) => {
+    jobIdRef.current = null;
+    pendingRef.current = [];
     statsRef.current = {
       totalSavedBytes: 0,
       totalOriginalSize: 0,
@@ -133,7 +181,21 @@ function useProgressTracker() {
     startTimer();
   };
 
-  return { progress, initProgress, processingRef };
+  /**
+   * Sets the job whose events are tracked and replays its held-back events.
+   *
+   * @param {string} jobId - Id returned by `optimize_images`
+   */
+  const setJobId = useCallback((jobId) => {
+    jobIdRef.current = jobId;
+    const pending = pendingRef.current;
+    pendingRef.current = [];
+    for (const [payload, apply] of pending) {
+      if (payload.jobId === jobId) apply(payload);
+    }
+  }, []);
+
+  return { progress, initProgress, setJobId, processingRef };
 }
 
 export default useProgressTracker;